@@ -0,0 +1,984 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    mask::Mask,
+    minefield::Minefield,
+    topology::{SquareTopology, Topology},
+    view::PlayerView,
+};
+
+/// The mark a player has placed on a hidden tile. Flags carry the number of mines the
+/// player thinks the tile holds.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum TileMark {
+    #[default]
+    None,
+    Flag(u8),
+    Question,
+}
+
+/// The rules used when placing the bombs on the board.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Variant {
+    /// Every bomb holds a single mine.
+    #[default]
+    Classic,
+
+    /// Every bomb holds between one and three mines and the numbers show the sum of the
+    /// mines around a tile.
+    MultiMine,
+}
+
+impl Variant {
+    /// Returns the most mines a single tile can hold.
+    pub fn max_mines(&self) -> u8 {
+        match self {
+            Variant::Classic => 1,
+            Variant::MultiMine => 3,
+        }
+    }
+}
+
+/// What happens to the bombs around the first tile that is revealed.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum FirstClick {
+    /// The first tile can be a bomb.
+    Anywhere,
+
+    /// The first tile is never a bomb.
+    #[default]
+    Safe,
+
+    /// The first tile and its neighbours are never bombs, so the first click always opens
+    /// up part of the board.
+    Opening,
+}
+
+#[derive(Copy, Clone, Serialize, Deserialize)]
+pub struct Tile {
+    pub revealed: bool,
+    pub bomb: bool,
+    /// Void tiles have been removed from the board by a mask.
+    pub void: bool,
+    /// The number of mines a bomb holds.
+    pub mines: u8,
+    pub mark: TileMark,
+    pub number: u8,
+}
+
+impl Tile {
+    pub fn flagged(&self) -> bool {
+        self.flags() > 0
+    }
+
+    /// Returns the number of mines the player has flagged on this tile.
+    pub fn flags(&self) -> u8 {
+        match self.mark {
+            TileMark::Flag(count) => count,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Action {
+    Flag,
+    Reveal,
+    Chord,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Game {
+    /// Every tile of the board, bombs included. Players should be shown the
+    /// `player_view` instead.
+    pub board: Vec<Vec<Tile>>,
+    pub game_over: bool,
+    pub game_won: bool,
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub bombs: usize,
+    pub question_marks: bool,
+    pub variant: Variant,
+    pub first_click: FirstClick,
+    pub mask: Option<Mask>,
+    /// Where the bombs go when the board was loaded instead of generated.
+    pub minefield: Option<Minefield>,
+    /// The bomb that ended the game, if one did.
+    pub exploded: Option<(usize, usize)>,
+    #[serde(skip, default = "default_topology")]
+    pub topology: Arc<dyn Topology>,
+    /// The boards before each action, so actions can be undone.
+    #[serde(skip)]
+    pub history: Vec<Vec<Vec<Tile>>>,
+    /// Places the bombs. Seeding it makes the same boards come up again.
+    #[serde(skip, default = "default_rng")]
+    rng: StdRng,
+}
+
+fn default_topology() -> Arc<dyn Topology> {
+    Arc::new(SquareTopology::default())
+}
+
+// Returns the neighbours of a tile on a board of the given width, height and depth,
+// leaving out the voids.
+pub(crate) fn layered_neighbours(
+    topology: &dyn Topology,
+    (width, height, depth): (usize, usize, usize),
+    (x, y): (usize, usize),
+    is_void: impl Fn(usize, usize) -> bool,
+) -> Vec<(usize, usize)> {
+    let (layer, row) = (y / height, y % height);
+    let flat: Vec<(usize, usize)> = topology
+        .neighbours(x, row, width, height)
+        .into_iter()
+        .filter(|(nx, ny)| !is_void(*nx, *ny))
+        .collect();
+    if depth == 1 {
+        return flat;
+    }
+
+    let mut neighbours = Vec::with_capacity(flat.len() * 3 + 2);
+    for z in layer.saturating_sub(1)..=(layer + 1).min(depth - 1) {
+        let offset = z * height;
+        if z != layer && !is_void(x, row) {
+            neighbours.push((x, row + offset));
+        }
+        neighbours.extend(flat.iter().map(|(nx, ny)| (*nx, ny + offset)));
+    }
+    neighbours
+}
+
+fn default_rng() -> StdRng {
+    StdRng::from_os_rng()
+}
+
+#[derive(Error, Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum GameError {
+    #[error("Cannot modify a visible tile")]
+    CannotModifyVisibleTile,
+    #[error("Cannot reveal a flagged tile")]
+    CannotRevealFlaggedTile,
+    #[error("Cannot chord a hidden tile")]
+    CannotChordHiddenTile,
+    #[error("Cannot modify a void tile")]
+    CannotModifyVoidTile,
+    #[error("Game is over")]
+    GameAlreadyOver,
+    #[error("Tile is outside the board")]
+    TileOutOfBounds,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum Response {
+    GameOver,
+    GameWon,
+    Flag,
+    Question,
+    Unflag,
+    Reveal(HashSet<(usize, usize)>),
+}
+
+impl Game {
+    pub fn new(width: usize, height: usize, bombs: usize) -> Game {
+        let mut game = Game {
+            board: Vec::new(),
+            game_over: false,
+            game_won: false,
+            width,
+            height,
+            depth: 1,
+            bombs,
+            question_marks: false,
+            variant: Variant::Classic,
+            first_click: FirstClick::default(),
+            mask: None,
+            minefield: None,
+            exploded: None,
+            topology: default_topology(),
+            history: Vec::new(),
+            rng: default_rng(),
+        };
+        game.reset();
+        game
+    }
+
+    /// Use the given topology to connect and lay out the tiles. This creates a new board.
+    pub fn with_topology(mut self, topology: Arc<dyn Topology>) -> Game {
+        self.topology = topology;
+        self.reset();
+        self
+    }
+
+    /// Stack the given number of layers on top of each other. Tiles are connected to the
+    /// tiles in the layers directly above and below them. The depth is ignored when there
+    /// is a minefield, since the minefield decides it. This creates a new board.
+    pub fn with_depth(mut self, depth: usize) -> Game {
+        if self.minefield.is_some() {
+            return self;
+        }
+        self.depth = depth.max(1);
+        self.reset();
+        self
+    }
+
+    /// Remove the voids of the given mask from the board. The mask decides the width and
+    /// height of the board and is applied to every layer. This creates a new board.
+    pub fn with_mask(mut self, mask: Mask) -> Game {
+        self.width = mask.width;
+        self.height = mask.height;
+        self.mask = Some(mask);
+        self.reset();
+        self
+    }
+
+    /// Place the bombs where the minefield has its mines instead of at random. The
+    /// minefield decides the width, height, depth and number of bombs, and its bombs stay
+    /// where they are on the first click. This creates a new board.
+    pub fn with_minefield(mut self, minefield: Minefield) -> Game {
+        self.width = minefield.width;
+        self.height = minefield.height;
+        self.depth = minefield.depth;
+        self.bombs = minefield.mines.len();
+        self.minefield = Some(minefield);
+        self.reset();
+        self
+    }
+
+    /// Returns true if the given tile has been removed from the board by the mask.
+    pub fn is_void(&self, x: usize, y: usize) -> bool {
+        match &self.mask {
+            Some(mask) => mask.is_void(x, y % self.height),
+            None => false,
+        }
+    }
+
+    /// Returns the number of tiles on the board, not counting voids.
+    pub fn tile_count(&self) -> usize {
+        let per_layer = match &self.mask {
+            Some(mask) => mask.tiles(),
+            None => self.width * self.height,
+        };
+        per_layer * self.depth
+    }
+
+    /// Use the given rules to place the bombs. This creates a new board.
+    pub fn with_variant(mut self, variant: Variant) -> Game {
+        self.variant = variant;
+        self.reset();
+        self
+    }
+
+    /// Place the bombs using the given seed, so the same boards can be played again. This
+    /// creates a new board.
+    pub fn with_seed(mut self, seed: u64) -> Game {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.reset();
+        self
+    }
+
+    /// Enable or disable the question mark state when cycling tile marks.
+    pub fn with_question_marks(mut self, enabled: bool) -> Game {
+        self.question_marks = enabled;
+        self
+    }
+
+    /// Use the given rule to keep bombs away from the first revealed tile.
+    pub fn with_first_click(mut self, first_click: FirstClick) -> Game {
+        self.first_click = first_click;
+        self
+    }
+
+    /// Change the size of each layer and the number of bombs. The size is ignored when
+    /// there is a mask, since the mask decides the size, and both are ignored when there
+    /// is a minefield. This creates a new board.
+    pub fn with_size(mut self, width: usize, height: usize, bombs: usize) -> Game {
+        if self.minefield.is_some() {
+            return self;
+        }
+        if self.mask.is_none() {
+            self.width = width;
+            self.height = height;
+        }
+        self.bombs = bombs;
+        self.reset();
+        self
+    }
+
+    /// Returns the number of rows on the board. The rows of each layer follow the rows of
+    /// the layer before it, so the tile `(x, y)` on layer `z` is in row `z * height + y`.
+    pub fn rows(&self) -> usize {
+        self.height * self.depth
+    }
+
+    pub fn initialize_board(&mut self) -> Vec<Vec<Tile>> {
+        let rows = self.rows();
+
+        // Create the tiles.
+        let mut tiles: Vec<Vec<Tile>> = (0..rows)
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| Tile {
+                        revealed: false,
+                        bomb: false,
+                        void: self.is_void(x, y),
+                        mines: 0,
+                        mark: TileMark::None,
+                        number: 0,
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Place the bombs of the minefield on the tiles that are on the board.
+        if let Some(minefield) = &self.minefield {
+            for &(x, y) in &minefield.mines {
+                if x < self.width && y < rows && !tiles[y][x].void {
+                    tiles[y][x].bomb = true;
+                    tiles[y][x].mines = 1;
+                }
+            }
+            self.calculate_numbers(&mut tiles);
+            return tiles;
+        }
+
+        // Place the bombs randomly, making sure there are enough tiles to hold them.
+        for _ in 0..self.bombs.min(self.tile_count()) {
+            let mut placed = false;
+            while !placed {
+                let x = self.rng.random_range(0..self.width);
+                let y = self.rng.random_range(0..rows);
+                if !tiles[y][x].bomb && !tiles[y][x].void {
+                    tiles[y][x].bomb = true;
+                    tiles[y][x].mines = self.rng.random_range(1..=self.variant.max_mines());
+                    placed = true;
+                }
+            }
+        }
+
+        self.calculate_numbers(&mut tiles);
+        tiles
+    }
+
+    /// Calculate the numbers for each tile.
+    fn calculate_numbers(&self, tiles: &mut [Vec<Tile>]) {
+        for y in 0..self.rows() {
+            for x in 0..self.width {
+                if tiles[y][x].bomb || tiles[y][x].void {
+                    tiles[y][x].number = 0;
+                    continue;
+                }
+                tiles[y][x].number = self
+                    .neighbours(x, y)
+                    .into_iter()
+                    .map(|(nx, ny)| tiles[ny][nx].mines)
+                    .sum();
+            }
+        }
+    }
+
+    /// Move the bombs away from the first revealed tile as the first click rule asks.
+    /// Bombs stay put when there is nowhere else to put them.
+    fn clear_first_click(&mut self, x: usize, y: usize) {
+        let mut protected = match self.first_click {
+            _ if self.minefield.is_some() => return,
+            FirstClick::Anywhere => return,
+            FirstClick::Safe => Vec::new(),
+            FirstClick::Opening => self.neighbours(x, y),
+        };
+        protected.push((x, y));
+
+        let mut free: Vec<(usize, usize)> = (0..self.rows())
+            .flat_map(|ty| (0..self.width).map(move |tx| (tx, ty)))
+            .filter(|&(tx, ty)| {
+                let tile = self.board[ty][tx];
+                !tile.bomb && !tile.void && !protected.contains(&(tx, ty))
+            })
+            .collect();
+        for (px, py) in protected {
+            if !self.board[py][px].bomb || free.is_empty() {
+                continue;
+            }
+            let (tx, ty) = free.swap_remove(self.rng.random_range(0..free.len()));
+            let mines = self.board[py][px].mines;
+            self.board[py][px].bomb = false;
+            self.board[py][px].mines = 0;
+            self.board[ty][tx].bomb = true;
+            self.board[ty][tx].mines = mines;
+        }
+
+        let mut board = std::mem::take(&mut self.board);
+        self.calculate_numbers(&mut board);
+        self.board = board;
+    }
+
+    pub fn reset(&mut self) {
+        self.board = self.initialize_board();
+        self.game_over = false;
+        self.game_won = false;
+        self.exploded = None;
+        self.history.clear();
+    }
+
+    /// Put the board back the way it was before the last action. Returns false when there
+    /// is nothing to undo or the game is over.
+    pub fn undo(&mut self) -> bool {
+        if self.game_over {
+            return false;
+        }
+        match self.history.pop() {
+            Some(board) => {
+                self.board = board;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns a hidden tile that is safe to reveal. Tiles next to a revealed tile are
+    /// picked first, so the hint helps with the part of the board being solved. Before
+    /// anything is revealed, tiles that open up part of the board are picked first. Games
+    /// with the same seed give the same hints.
+    pub fn hint(&mut self) -> Option<(usize, usize)> {
+        if self.game_over {
+            return None;
+        }
+        let safe: Vec<(usize, usize)> = (0..self.rows())
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                let tile = self.board[y][x];
+                !tile.revealed && !tile.bomb && !tile.void && !tile.flagged()
+            })
+            .collect();
+        let frontier: Vec<(usize, usize)> = safe
+            .iter()
+            .copied()
+            .filter(|&(x, y)| {
+                self.neighbours(x, y)
+                    .into_iter()
+                    .any(|(nx, ny)| self.board[ny][nx].revealed)
+            })
+            .collect();
+        let openings: Vec<(usize, usize)> = safe
+            .iter()
+            .copied()
+            .filter(|&(x, y)| self.board[y][x].number == 0)
+            .collect();
+
+        let candidates = [frontier, openings, safe]
+            .into_iter()
+            .find(|candidates| !candidates.is_empty())?;
+        Some(candidates[self.rng.random_range(0..candidates.len())])
+    }
+
+    pub fn tile(&self, x: usize, y: usize) -> Tile {
+        self.board[y][x]
+    }
+
+    pub fn tile_number(&self, x: usize, y: usize) -> u8 {
+        if x < self.width && y < self.rows() {
+            self.board[y][x].number
+        } else {
+            0
+        }
+    }
+
+    pub fn perform_action(&mut self, x: usize, y: usize, action: Action) -> Result<Response, GameError> {
+        if self.game_over {
+            return Err(GameError::GameAlreadyOver);
+        }
+        if x >= self.width || y >= self.rows() {
+            return Err(GameError::TileOutOfBounds);
+        }
+        if self.board[y][x].void {
+            return Err(GameError::CannotModifyVoidTile);
+        }
+
+        let before = self.board.clone();
+        let response = match action {
+            Action::Flag => {
+                if self.board[y][x].revealed {
+                    return Err(GameError::CannotModifyVisibleTile);
+                }
+                // Cycle through the flag counts and then the marks, skipping the question
+                // mark if it's disabled.
+                let (mark, response) = match self.board[y][x].mark {
+                    TileMark::None => (TileMark::Flag(1), Response::Flag),
+                    TileMark::Flag(count) if count < self.variant.max_mines() => {
+                        (TileMark::Flag(count + 1), Response::Flag)
+                    }
+                    TileMark::Flag(_) if self.question_marks => {
+                        (TileMark::Question, Response::Question)
+                    }
+                    TileMark::Flag(_) | TileMark::Question => (TileMark::None, Response::Unflag),
+                };
+                self.board[y][x].mark = mark;
+                response
+            }
+            Action::Reveal => {
+                if self.board[y][x].flagged() {
+                    return Err(GameError::CannotRevealFlaggedTile);
+                }
+                if self.board.iter().flatten().all(|tile| !tile.revealed) {
+                    self.clear_first_click(x, y);
+                }
+                self.reveal(&[(x, y)])
+            }
+            Action::Chord => {
+                if !self.board[y][x].revealed {
+                    return Err(GameError::CannotChordHiddenTile);
+                }
+
+                // We only chord when the number of flags around the tile matches its number.
+                let neighbours = self.neighbours(x, y);
+                let flags: u8 = neighbours
+                    .iter()
+                    .map(|(nx, ny)| self.board[*ny][*nx].flags())
+                    .sum();
+                if flags != self.board[y][x].number {
+                    return Ok(Response::Reveal(HashSet::new()));
+                }
+
+                // Question marks don't count as flags, so those tiles are revealed as well.
+                let targets: Vec<(usize, usize)> = neighbours
+                    .into_iter()
+                    .filter(|(nx, ny)| {
+                        let tile = self.board[*ny][*nx];
+                        !tile.revealed && !tile.flagged()
+                    })
+                    .collect();
+                self.reveal(&targets)
+            }
+        };
+
+        // Actions that don't change anything, such as a chord without enough flags,
+        // aren't worth undoing.
+        if !matches!(response, Response::Reveal(ref tiles) if tiles.is_empty()) {
+            self.history.push(before);
+        }
+        Ok(response)
+    }
+
+    /// Reveal the given tiles, ending the game if any of them is a bomb.
+    fn reveal(&mut self, targets: &[(usize, usize)]) -> Response {
+        if let Some(&bomb) = targets.iter().find(|(x, y)| self.board[*y][*x].bomb) {
+            self.exploded = Some(bomb);
+            self.finish_game(false);
+            return Response::GameOver;
+        }
+
+        let mut tiles = HashSet::new();
+        for (x, y) in targets {
+            self.reveal_tiles_recursively(&mut tiles, *x, *y);
+        }
+        if self.all_tiles_revealed() {
+            self.finish_game(true);
+            Response::GameWon
+        } else {
+            Response::Reveal(tiles)
+        }
+    }
+
+    /// Returns the coordinates of all the tiles surrounding the given tile. This includes
+    /// the tiles in the same position in the layers above and below and their neighbours.
+    /// Voids are never neighbours.
+    pub fn neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        layered_neighbours(
+            self.topology.as_ref(),
+            (self.width, self.height, self.depth),
+            (x, y),
+            |x, y| self.is_void(x, y),
+        )
+    }
+
+    /// Returns what the player can see of the game. Use it instead of the board when
+    /// showing the game to a player, so the bombs stay hidden.
+    pub fn player_view(&self) -> PlayerView {
+        PlayerView::new(self)
+    }
+
+    /// Returns the 3BV of the board, the fewest clicks needed to reveal every safe tile
+    /// without chording. Each opening takes one click, as does each safe tile that isn't
+    /// revealed by an opening.
+    pub fn three_bv(&self) -> usize {
+        let mut counted = vec![vec![false; self.width]; self.rows()];
+        let mut clicks = 0;
+
+        // Flood each opening, counting the zeros in it and the numbers around them.
+        for y in 0..self.rows() {
+            for x in 0..self.width {
+                let tile = self.board[y][x];
+                if counted[y][x] || tile.bomb || tile.void || tile.number != 0 {
+                    continue;
+                }
+                clicks += 1;
+                counted[y][x] = true;
+                let mut stack = vec![(x, y)];
+                while let Some((tx, ty)) = stack.pop() {
+                    if self.board[ty][tx].number != 0 {
+                        continue;
+                    }
+                    for (nx, ny) in self.neighbours(tx, ty) {
+                        if !counted[ny][nx] {
+                            counted[ny][nx] = true;
+                            stack.push((nx, ny));
+                        }
+                    }
+                }
+            }
+        }
+
+        let singles = (0..self.rows())
+            .flat_map(|y| (0..self.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                let tile = self.board[y][x];
+                !counted[y][x] && !tile.bomb && !tile.void
+            })
+            .count();
+        clicks + singles
+    }
+
+    /// Returns the share of the safe tiles that have been revealed, from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        let safe = self
+            .board
+            .iter()
+            .flatten()
+            .filter(|tile| !tile.bomb && !tile.void);
+        let revealed = safe.clone().filter(|tile| tile.revealed).count();
+        revealed as f32 / safe.count().max(1) as f32
+    }
+
+    pub fn all_tiles_revealed(&self) -> bool {
+        for row in &self.board {
+            for tile in row {
+                if !tile.revealed && !tile.bomb && !tile.void {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Reveal the tile and, if it's an opening, the tiles around it. Openings are flooded
+    /// with a stack instead of recursion, so big boards can't overflow the thread's stack.
+    pub fn reveal_tiles_recursively(
+        &mut self,
+        tiles: &mut HashSet<(usize, usize)>,
+        x: usize,
+        y: usize,
+    ) {
+        let mut stack = vec![(x, y)];
+        while let Some((x, y)) = stack.pop() {
+            // Flagged tiles are left alone, but question marks are cleared when revealed.
+            if self.board[y][x].revealed || self.board[y][x].flagged() {
+                continue;
+            }
+
+            self.board[y][x].revealed = true;
+            self.board[y][x].mark = TileMark::None;
+            tiles.insert((x, y));
+
+            if self.board[y][x].number == 0 {
+                stack.extend(self.neighbours(x, y));
+            }
+        }
+    }
+
+    pub fn finish_game(&mut self, won: bool) {
+        self.game_over = true;
+        self.game_won = won;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns a game on the board drawn as ASCII art, where `*` is a mine.
+    fn game(board: &str) -> Game {
+        let minefield = Minefield::parse(board).unwrap();
+        Game::new(1, 1, 0).with_minefield(minefield)
+    }
+
+    #[test]
+    fn flags_cycle_without_question_marks() {
+        let mut game = game("*..\n...\n...");
+        assert!(matches!(
+            game.perform_action(0, 0, Action::Flag),
+            Ok(Response::Flag)
+        ));
+        assert_eq!(game.tile(0, 0).mark, TileMark::Flag(1));
+        assert!(matches!(
+            game.perform_action(0, 0, Action::Flag),
+            Ok(Response::Unflag)
+        ));
+        assert_eq!(game.tile(0, 0).mark, TileMark::None);
+    }
+
+    #[test]
+    fn flags_cycle_through_question_marks() {
+        let mut game = game("*..\n...\n...").with_question_marks(true);
+        game.perform_action(0, 0, Action::Flag).unwrap();
+        assert!(matches!(
+            game.perform_action(0, 0, Action::Flag),
+            Ok(Response::Question)
+        ));
+        assert_eq!(game.tile(0, 0).mark, TileMark::Question);
+        assert!(matches!(
+            game.perform_action(0, 0, Action::Flag),
+            Ok(Response::Unflag)
+        ));
+        assert_eq!(game.tile(0, 0).mark, TileMark::None);
+    }
+
+    #[test]
+    fn flagged_tiles_can_not_be_revealed_but_question_marks_can() {
+        let mut game = game("*...\n....").with_question_marks(true);
+        game.perform_action(3, 1, Action::Flag).unwrap();
+        assert_eq!(
+            game.perform_action(3, 1, Action::Reveal).unwrap_err(),
+            GameError::CannotRevealFlaggedTile
+        );
+        game.perform_action(3, 1, Action::Flag).unwrap();
+        game.perform_action(3, 1, Action::Reveal).unwrap();
+        assert!(game.tile(3, 1).revealed);
+        assert_eq!(game.tile(3, 1).mark, TileMark::None);
+    }
+
+    #[test]
+    fn revealed_tiles_can_not_be_marked() {
+        let mut game = game("*...\n....");
+        game.perform_action(3, 1, Action::Reveal).unwrap();
+        assert_eq!(
+            game.perform_action(3, 1, Action::Flag).unwrap_err(),
+            GameError::CannotModifyVisibleTile
+        );
+    }
+
+    #[test]
+    fn chording_reveals_the_neighbours_once_the_flags_match() {
+        let mut game = game("*.....\n......\n......\n.....*\n....*.");
+        game.perform_action(1, 1, Action::Reveal).unwrap();
+        assert_eq!(game.tile(1, 1).number, 1);
+
+        // Without the flag the chord does nothing.
+        let response = game.perform_action(1, 1, Action::Chord).unwrap();
+        assert!(matches!(response, Response::Reveal(tiles) if tiles.is_empty()));
+
+        game.perform_action(0, 0, Action::Flag).unwrap();
+        let response = game.perform_action(1, 1, Action::Chord).unwrap();
+        assert!(matches!(response, Response::Reveal(tiles) if tiles.contains(&(2, 2))));
+        assert!(!game.game_over);
+        assert!(game.tile(0, 1).revealed && game.tile(2, 0).revealed);
+    }
+
+    #[test]
+    fn chording_around_a_wrong_flag_loses() {
+        let mut game = game("*...\n....\n....");
+        game.perform_action(1, 1, Action::Reveal).unwrap();
+        game.perform_action(2, 2, Action::Flag).unwrap();
+        assert!(matches!(
+            game.perform_action(1, 1, Action::Chord),
+            Ok(Response::GameOver)
+        ));
+        assert!(game.game_over && !game.game_won);
+    }
+
+    #[test]
+    fn hidden_tiles_can_not_be_chorded() {
+        let mut game = game("*...\n....");
+        assert_eq!(
+            game.perform_action(3, 1, Action::Chord).unwrap_err(),
+            GameError::CannotChordHiddenTile
+        );
+    }
+
+    #[test]
+    fn revealing_every_safe_tile_wins() {
+        let mut game = game("*...\n*...");
+        assert!(matches!(
+            game.perform_action(3, 1, Action::Reveal),
+            Ok(Response::GameWon)
+        ));
+        assert!(game.game_over && game.game_won);
+        assert_eq!(
+            game.perform_action(3, 0, Action::Reveal).unwrap_err(),
+            GameError::GameAlreadyOver
+        );
+    }
+
+    #[test]
+    fn layers_follow_each_other_as_rows() {
+        let game = Game::new(3, 2, 0).with_depth(3);
+        assert_eq!(game.rows(), 6);
+        assert_eq!(game.board.len(), 6);
+        assert_eq!(game.tile_count(), 18);
+    }
+
+    #[test]
+    fn neighbours_reach_the_layers_above_and_below() {
+        let game = Game::new(3, 3, 0).with_depth(3);
+        assert_eq!(game.neighbours(1, 4).len(), 26);
+
+        // A corner of the bottom layer only sees the layer above it.
+        let corner = game.neighbours(0, 0);
+        assert_eq!(corner.len(), 7);
+        assert!(corner.contains(&(0, 3)) && corner.contains(&(1, 4)));
+        assert!(!corner.iter().any(|&(_, y)| y >= 6));
+    }
+
+    #[test]
+    fn numbers_count_the_bombs_in_other_layers() {
+        let mut game = Game::new(3, 3, 0).with_depth(2);
+        game.board[1][1].bomb = true;
+        game.board[1][1].mines = 1;
+        let mut board = std::mem::take(&mut game.board);
+        game.calculate_numbers(&mut board);
+        game.board = board;
+        assert_eq!(game.tile(1, 4).number, 1);
+        assert_eq!(game.tile(0, 3).number, 1);
+        assert_eq!(game.tile(0, 0).number, 1);
+    }
+
+    #[test]
+    fn multi_mine_flags_stack_before_cycling_on() {
+        let mut game = game("*..\n...").with_variant(Variant::MultiMine);
+        for count in 1..=3 {
+            assert!(matches!(
+                game.perform_action(0, 0, Action::Flag),
+                Ok(Response::Flag)
+            ));
+            assert_eq!(game.tile(0, 0).flags(), count);
+        }
+        assert!(matches!(
+            game.perform_action(0, 0, Action::Flag),
+            Ok(Response::Unflag)
+        ));
+        assert!(!game.tile(0, 0).flagged());
+    }
+
+    #[test]
+    fn multi_mine_numbers_sum_the_mines_around_them() {
+        let mut game = game("*.*\n...").with_variant(Variant::MultiMine);
+        game.board[0][0].mines = 3;
+        game.board[0][2].mines = 2;
+        let mut board = std::mem::take(&mut game.board);
+        game.calculate_numbers(&mut board);
+        game.board = board;
+        assert_eq!(game.tile(1, 1).number, 5);
+        assert_eq!(game.tile(0, 1).number, 3);
+
+        // Chording needs the flags to add up to the number.
+        game.perform_action(0, 1, Action::Reveal).unwrap();
+        game.perform_action(0, 0, Action::Flag).unwrap();
+        let response = game.perform_action(0, 1, Action::Chord).unwrap();
+        assert!(matches!(response, Response::Reveal(tiles) if tiles.is_empty()));
+        game.perform_action(0, 0, Action::Flag).unwrap();
+        game.perform_action(0, 0, Action::Flag).unwrap();
+        let response = game.perform_action(0, 1, Action::Chord).unwrap();
+        assert!(matches!(response, Response::Reveal(tiles) if tiles.contains(&(1, 0))));
+    }
+
+    #[test]
+    fn multi_mine_bombs_hold_up_to_three_mines() {
+        let game = Game::new(10, 10, 40)
+            .with_variant(Variant::MultiMine)
+            .with_seed(3);
+        let bombs: Vec<Tile> = game
+            .board
+            .iter()
+            .flatten()
+            .filter(|t| t.bomb)
+            .copied()
+            .collect();
+        assert_eq!(bombs.len(), 40);
+        assert!(bombs.iter().all(|tile| (1..=3).contains(&tile.mines)));
+    }
+
+    #[test]
+    fn seeded_games_give_the_same_hints() {
+        let mut first = Game::new(16, 16, 40).with_seed(11);
+        let mut second = Game::new(16, 16, 40).with_seed(11);
+        for _ in 0..5 {
+            let hint = first.hint();
+            assert_eq!(hint, second.hint());
+            let (x, y) = hint.unwrap();
+            assert!(!first.tile(x, y).bomb);
+            first.perform_action(x, y, Action::Reveal).unwrap();
+            second.perform_action(x, y, Action::Reveal).unwrap();
+        }
+    }
+
+    #[test]
+    fn the_first_click_is_safe_by_default() {
+        for seed in 0..20 {
+            let mut game = Game::new(5, 5, 20).with_seed(seed);
+            game.perform_action(2, 2, Action::Reveal).unwrap();
+            assert!(!game.game_over, "seed {}", seed);
+            assert_eq!(game.board.iter().flatten().filter(|t| t.bomb).count(), 20);
+        }
+    }
+
+    #[test]
+    fn the_first_click_opens_the_board_with_openings() {
+        for seed in 0..20 {
+            let mut game = Game::new(6, 6, 20)
+                .with_seed(seed)
+                .with_first_click(FirstClick::Opening);
+            game.perform_action(2, 2, Action::Reveal).unwrap();
+            assert_eq!(game.tile(2, 2).number, 0, "seed {}", seed);
+            assert!(
+                game.neighbours(2, 2)
+                    .iter()
+                    .all(|&(x, y)| game.tile(x, y).revealed)
+            );
+        }
+    }
+
+    #[test]
+    fn the_first_click_can_lose_anywhere() {
+        let mut game = game("*.\n..").with_first_click(FirstClick::Anywhere);
+        game.minefield = None;
+        assert!(matches!(
+            game.perform_action(0, 0, Action::Reveal),
+            Ok(Response::GameOver)
+        ));
+    }
+
+    #[test]
+    fn minefield_bombs_stay_put_on_the_first_click() {
+        let mut game = game("*.\n..");
+        assert!(matches!(
+            game.perform_action(0, 0, Action::Reveal),
+            Ok(Response::GameOver)
+        ));
+    }
+
+    #[test]
+    fn bombs_stay_put_when_there_is_nowhere_to_move_them() {
+        let mut game = Game::new(2, 2, 3).with_first_click(FirstClick::Opening);
+        game.perform_action(0, 0, Action::Reveal).unwrap();
+        assert_eq!(game.board.iter().flatten().filter(|t| t.bomb).count(), 3);
+    }
+
+    #[test]
+    fn large_openings_are_revealed_without_overflowing() {
+        let mut game = Game::new(400, 400, 1).with_first_click(FirstClick::Anywhere);
+        let (x, y) = match game.tile(0, 0).bomb {
+            true => (399, 399),
+            false => (0, 0),
+        };
+        let handle = std::thread::Builder::new()
+            .stack_size(256 * 1024)
+            .spawn(move || game.perform_action(x, y, Action::Reveal).is_ok());
+        assert!(handle.unwrap().join().unwrap());
+    }
+}
@@ -0,0 +1,18 @@
+//! The rules of Minesweeper without any rendering, so tools and bots can play the game
+//! without pulling in Bevy.
+
+pub mod game;
+pub mod mask;
+pub mod minefield;
+pub mod protocol;
+pub mod solver;
+pub mod topology;
+pub mod view;
+
+pub use game::{Action, FirstClick, Game, GameError, Response, Tile, TileMark, Variant};
+pub use mask::{Mask, MaskError};
+pub use minefield::{Minefield, MinefieldError, MinefieldFormat};
+pub use protocol::{Event, Partner, Request, Standing};
+pub use solver::Solver;
+pub use topology::{HexTopology, SquareTopology, TileShape, Topology, TopologyDescriptor};
+pub use view::{PlayerView, VisibleTile};
@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// A `Mask` removes tiles from a rectangular board so boards can take other shapes. The
+/// removed tiles are called voids.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mask {
+    pub width: usize,
+    pub height: usize,
+    voids: Vec<Vec<bool>>,
+}
+
+#[derive(Error, Debug)]
+pub enum MaskError {
+    #[error("The mask doesn't contain any tiles")]
+    Empty,
+    #[error("Unable to read the mask: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Unable to decode the mask image: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+impl Mask {
+    /// Load a mask from the given file. PNG files are decoded as images and everything
+    /// else is parsed as ASCII art.
+    pub fn load(path: &Path) -> Result<Mask, MaskError> {
+        let is_png = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+        match is_png {
+            true => Self::from_png(&std::fs::read(path)?),
+            false => Self::parse(&std::fs::read_to_string(path)?),
+        }
+    }
+
+    /// Parse a mask from ASCII art. Each line is a row, where `.` and spaces are voids and
+    /// any other character is a tile. Short rows are padded with voids.
+    pub fn parse(text: &str) -> Result<Mask, MaskError> {
+        let lines: Vec<&str> = text.lines().map(|line| line.trim_end()).collect();
+
+        // Ignore the empty lines at the start and the end.
+        let first = lines.iter().position(|line| !line.is_empty());
+        let last = lines.iter().rposition(|line| !line.is_empty());
+        let lines = match (first, last) {
+            (Some(first), Some(last)) => &lines[first..=last],
+            _ => return Err(MaskError::Empty),
+        };
+
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let voids = lines
+            .iter()
+            .map(|line| {
+                let mut row: Vec<bool> = line.chars().map(|c| c == '.' || c == ' ').collect();
+                row.resize(width, true);
+                row
+            })
+            .collect();
+        Self::new(voids)
+    }
+
+    /// Decode a mask from a PNG image where dark or transparent pixels are voids.
+    pub fn from_png(bytes: &[u8]) -> Result<Mask, MaskError> {
+        let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)?;
+        let image = image.to_rgba8();
+        let voids: Vec<Vec<bool>> = image
+            .rows()
+            .map(|row| {
+                row.map(|pixel| {
+                    let [r, g, b, a] = pixel.0;
+                    let luminance = 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+                    a < 128 || luminance < 128.0
+                })
+                .collect()
+            })
+            .collect();
+        Self::new(voids)
+    }
+
+    fn new(voids: Vec<Vec<bool>>) -> Result<Mask, MaskError> {
+        let mask = Mask {
+            width: voids.first().map(|row| row.len()).unwrap_or(0),
+            height: voids.len(),
+            voids,
+        };
+        match mask.tiles() {
+            0 => Err(MaskError::Empty),
+            _ => Ok(mask),
+        }
+    }
+
+    /// Returns true if the given tile has been removed from the board.
+    pub fn is_void(&self, x: usize, y: usize) -> bool {
+        self.voids[y][x]
+    }
+
+    /// Returns the number of tiles that are left on the board.
+    pub fn tiles(&self) -> usize {
+        self.voids.iter().flatten().filter(|void| !**void).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Game;
+
+    #[test]
+    fn parse_pads_short_rows_with_voids() {
+        let mask = Mask::parse("\n\n###\n#.\n# #\n\n").unwrap();
+        assert_eq!((mask.width, mask.height), (3, 3));
+        assert!(!mask.is_void(0, 1));
+        assert!(mask.is_void(1, 1) && mask.is_void(2, 1) && mask.is_void(1, 2));
+        assert_eq!(mask.tiles(), 6);
+    }
+
+    #[test]
+    fn masks_need_a_tile() {
+        assert!(matches!(Mask::parse(""), Err(MaskError::Empty)));
+        assert!(matches!(Mask::parse("...\n. ."), Err(MaskError::Empty)));
+    }
+
+    #[test]
+    fn dark_and_transparent_pixels_are_voids() {
+        let mut image = image::RgbaImage::new(3, 1);
+        image.put_pixel(0, 0, image::Rgba([255, 255, 255, 255]));
+        image.put_pixel(1, 0, image::Rgba([0, 0, 0, 255]));
+        image.put_pixel(2, 0, image::Rgba([255, 255, 255, 0]));
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+
+        let mask = Mask::from_png(bytes.get_ref()).unwrap();
+        assert_eq!((mask.width, mask.height), (3, 1));
+        assert!(!mask.is_void(0, 0) && mask.is_void(1, 0) && mask.is_void(2, 0));
+    }
+
+    #[test]
+    fn voids_hold_no_bombs_and_are_never_neighbours() {
+        let mask = Mask::parse("##.\n#..\n###").unwrap();
+        let game = Game::new(1, 1, 5).with_mask(mask).with_seed(7);
+        assert_eq!(game.tile_count(), 6);
+        assert!(
+            game.board
+                .iter()
+                .flatten()
+                .all(|tile| !(tile.void && tile.bomb))
+        );
+        assert_eq!(game.neighbours(0, 1), vec![(0, 0), (1, 0), (0, 2), (1, 2)]);
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::game::Game;
+
+/// The files boards can be saved to and loaded from.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum MinefieldFormat {
+    /// ASCII art with a line per row, where `*` is a mine and `.` is a safe tile. Layers
+    /// are separated by an empty line.
+    #[default]
+    Ascii,
+
+    /// The binary list of mine coordinates read by Minesweeper Arbiter and Minesweeper
+    /// Clone. It starts with the width and height as bytes and the number of mines as a
+    /// big endian `u16`, followed by a byte for the column and row of each mine.
+    Mbf,
+}
+
+impl MinefieldFormat {
+    /// Returns the format of the given file, which is MBF for `.mbf` files and ASCII art
+    /// for everything else.
+    pub fn of(path: &Path) -> MinefieldFormat {
+        let is_mbf = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("mbf"));
+        match is_mbf {
+            true => MinefieldFormat::Mbf,
+            false => MinefieldFormat::Ascii,
+        }
+    }
+}
+
+/// A `Minefield` is a board with its mines already placed, so boards can be shared and
+/// played again by anyone.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Minefield {
+    pub width: usize,
+    pub height: usize,
+
+    /// The number of layers. Their rows follow each other like they do in a `Game`.
+    pub depth: usize,
+
+    /// The column and row of each mine, counting the rows of every layer.
+    pub mines: HashSet<(usize, usize)>,
+}
+
+#[derive(Error, Debug)]
+pub enum MinefieldError {
+    #[error("The minefield doesn't contain any tiles")]
+    Empty,
+    #[error("The minefield ends before all of its mines are listed")]
+    Truncated,
+    #[error("The mine at ({0}, {1}) is outside the minefield")]
+    OutOfBounds(usize, usize),
+    #[error("MBF files hold boards up to 255 by 255 tiles with up to 65535 mines")]
+    TooLarge,
+    #[error("MBF files can't hold boards with more than one layer")]
+    Layered,
+    #[error("Unable to read the minefield: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl Minefield {
+    /// Load a minefield from the given file, in the format its extension asks for.
+    pub fn load(path: &Path) -> Result<Minefield, MinefieldError> {
+        match MinefieldFormat::of(path) {
+            MinefieldFormat::Ascii => Self::parse(&std::fs::read_to_string(path)?),
+            MinefieldFormat::Mbf => Self::from_mbf(&std::fs::read(path)?),
+        }
+    }
+
+    /// Save the minefield to the given file, in the format its extension asks for.
+    pub fn save(&self, path: &Path) -> Result<(), MinefieldError> {
+        std::fs::write(path, self.to_bytes(MinefieldFormat::of(path))?)?;
+        Ok(())
+    }
+
+    /// Returns the minefield written in the given format.
+    pub fn to_bytes(&self, format: MinefieldFormat) -> Result<Vec<u8>, MinefieldError> {
+        match format {
+            MinefieldFormat::Ascii => Ok(self.to_ascii().into_bytes()),
+            MinefieldFormat::Mbf => self.to_mbf(),
+        }
+    }
+
+    /// Returns the mines of the game, layers included.
+    pub fn from_game(game: &Game) -> Minefield {
+        let mines = (0..game.rows())
+            .flat_map(|y| (0..game.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| game.tile(x, y).bomb)
+            .collect();
+        Minefield {
+            width: game.width,
+            height: game.height,
+            depth: game.depth,
+            mines,
+        }
+    }
+
+    /// Parse a minefield from ASCII art. Each line is a row, where `*` is a mine and any
+    /// other character is a safe tile, and layers are separated by empty lines. Short rows
+    /// and layers are padded with safe tiles.
+    pub fn parse(text: &str) -> Result<Minefield, MinefieldError> {
+        let lines: Vec<&str> = text.lines().map(|line| line.trim_end()).collect();
+
+        // Ignore the empty lines at the start and the end.
+        let first = lines.iter().position(|line| !line.is_empty());
+        let last = lines.iter().rposition(|line| !line.is_empty());
+        let lines = match (first, last) {
+            (Some(first), Some(last)) => &lines[first..=last],
+            _ => return Err(MinefieldError::Empty),
+        };
+
+        let layers: Vec<&[&str]> = lines
+            .split(|line| line.is_empty())
+            .filter(|layer| !layer.is_empty())
+            .collect();
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let height = layers.iter().map(|layer| layer.len()).max().unwrap_or(0);
+        let mines = layers
+            .iter()
+            .enumerate()
+            .flat_map(|(z, layer)| {
+                layer.iter().enumerate().flat_map(move |(row, line)| {
+                    line.chars()
+                        .enumerate()
+                        .filter(|(_, c)| *c == '*')
+                        .map(move |(x, _)| (x, z * height + row))
+                })
+            })
+            .collect();
+        Self::new(width, height, layers.len(), mines)
+    }
+
+    /// Decode a minefield from an MBF file.
+    pub fn from_mbf(bytes: &[u8]) -> Result<Minefield, MinefieldError> {
+        let (header, coordinates) = match bytes.split_at_checked(4) {
+            Some(parts) => parts,
+            None => return Err(MinefieldError::Truncated),
+        };
+        let count = u16::from_be_bytes([header[2], header[3]]) as usize;
+        if coordinates.len() < count * 2 {
+            return Err(MinefieldError::Truncated);
+        }
+        let mines = coordinates
+            .chunks_exact(2)
+            .take(count)
+            .map(|mine| (mine[0] as usize, mine[1] as usize))
+            .collect();
+        Self::new(header[0] as usize, header[1] as usize, 1, mines)
+    }
+
+    fn new(
+        width: usize,
+        height: usize,
+        depth: usize,
+        mines: Vec<(usize, usize)>,
+    ) -> Result<Minefield, MinefieldError> {
+        if width == 0 || height == 0 || depth == 0 {
+            return Err(MinefieldError::Empty);
+        }
+        let rows = height * depth;
+        if let Some(&(x, y)) = mines.iter().find(|&&(x, y)| x >= width || y >= rows) {
+            return Err(MinefieldError::OutOfBounds(x, y));
+        }
+        Ok(Minefield {
+            width,
+            height,
+            depth,
+            mines: mines.into_iter().collect(),
+        })
+    }
+
+    /// Returns the number of rows in the minefield, counting every layer.
+    pub fn rows(&self) -> usize {
+        self.height * self.depth
+    }
+
+    /// Returns true if there is a mine on the given tile.
+    pub fn is_mine(&self, x: usize, y: usize) -> bool {
+        self.mines.contains(&(x, y))
+    }
+
+    /// Returns the minefield as ASCII art, with `*` for mines and `.` for safe tiles and an
+    /// empty line between layers.
+    pub fn to_ascii(&self) -> String {
+        let mut text = String::with_capacity((self.width + 1) * self.rows() + self.depth);
+        for y in 0..self.rows() {
+            if y > 0 && y % self.height == 0 {
+                text.push('\n');
+            }
+            text.extend((0..self.width).map(|x| match self.is_mine(x, y) {
+                true => '*',
+                false => '.',
+            }));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Returns the minefield as an MBF file, with the mines in reading order.
+    pub fn to_mbf(&self) -> Result<Vec<u8>, MinefieldError> {
+        if self.depth > 1 {
+            return Err(MinefieldError::Layered);
+        }
+        let width = u8::try_from(self.width).map_err(|_| MinefieldError::TooLarge)?;
+        let height = u8::try_from(self.height).map_err(|_| MinefieldError::TooLarge)?;
+        let count = u16::try_from(self.mines.len()).map_err(|_| MinefieldError::TooLarge)?;
+
+        let mut bytes = Vec::with_capacity(4 + self.mines.len() * 2);
+        bytes.extend([width, height]);
+        bytes.extend(count.to_be_bytes());
+        let mut mines: Vec<(usize, usize)> = self.mines.iter().copied().collect();
+        mines.sort_unstable_by_key(|&(x, y)| (y, x));
+        for (x, y) in mines {
+            bytes.extend([x as u8, y as u8]);
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOARD: &str = "*....\n..*..\n....*\n";
+
+    #[test]
+    fn ascii_round_trips() {
+        let minefield = Minefield::parse(BOARD).unwrap();
+        assert_eq!(
+            (minefield.width, minefield.height, minefield.depth),
+            (5, 3, 1)
+        );
+        assert_eq!(minefield.mines, HashSet::from([(0, 0), (2, 1), (4, 2)]));
+        assert_eq!(minefield.to_ascii(), BOARD);
+        assert_eq!(Minefield::parse(&minefield.to_ascii()).unwrap(), minefield);
+    }
+
+    #[test]
+    fn mbf_round_trips() {
+        let minefield = Minefield::parse(BOARD).unwrap();
+        let bytes = minefield.to_mbf().unwrap();
+        assert_eq!(bytes, [5, 3, 0, 3, 0, 0, 2, 1, 4, 2]);
+        assert_eq!(Minefield::from_mbf(&bytes).unwrap(), minefield);
+    }
+
+    #[test]
+    fn layers_are_separated_by_empty_lines() {
+        let minefield = Minefield::parse("*..\n...\n\n..\n.*").unwrap();
+        assert_eq!(
+            (minefield.width, minefield.height, minefield.depth),
+            (3, 2, 2)
+        );
+        assert!(minefield.is_mine(0, 0) && minefield.is_mine(1, 3));
+        assert_eq!(minefield.to_ascii(), "*..\n...\n\n...\n.*.\n");
+        assert!(matches!(minefield.to_mbf(), Err(MinefieldError::Layered)));
+    }
+
+    #[test]
+    fn games_keep_their_layers() {
+        let game = Game::new(4, 3, 6).with_depth(2).with_seed(5);
+        let minefield = Minefield::from_game(&game);
+        assert_eq!(minefield.depth, 2);
+        assert_eq!(minefield.mines.len(), 6);
+
+        let copy =
+            Game::new(1, 1, 0).with_minefield(Minefield::parse(&minefield.to_ascii()).unwrap());
+        assert_eq!((copy.width, copy.height, copy.depth), (4, 3, 2));
+        for y in 0..game.rows() {
+            for x in 0..game.width {
+                assert_eq!(copy.tile(x, y).bomb, game.tile(x, y).bomb);
+                assert_eq!(copy.tile(x, y).number, game.tile(x, y).number);
+            }
+        }
+    }
+
+    #[test]
+    fn broken_files_are_rejected() {
+        assert!(matches!(
+            Minefield::parse("\n\n"),
+            Err(MinefieldError::Empty)
+        ));
+        assert!(matches!(
+            Minefield::from_mbf(&[2, 2, 0]),
+            Err(MinefieldError::Truncated)
+        ));
+        assert!(matches!(
+            Minefield::from_mbf(&[2, 2, 0, 2, 0, 0]),
+            Err(MinefieldError::Truncated)
+        ));
+        assert!(matches!(
+            Minefield::from_mbf(&[2, 2, 0, 1, 3, 0]),
+            Err(MinefieldError::OutOfBounds(3, 0))
+        ));
+
+        let wide = Minefield::parse(&".".repeat(256)).unwrap();
+        assert!(matches!(wide.to_mbf(), Err(MinefieldError::TooLarge)));
+    }
+}
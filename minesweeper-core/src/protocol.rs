@@ -0,0 +1,108 @@
+//! The messages a program outside the game uses to play it. Each message is written as a
+//! single line of JSON, so bots can be written in any language.
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Action, GameError, Response};
+use crate::view::PlayerView;
+
+/// A message sent to the game by the player.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Request {
+    /// Do an action on the tile at the given column and row.
+    Action { x: usize, y: usize, action: Action },
+
+    /// Point at the tile at the given column and row. The cursor is shown to the other
+    /// players in a cooperative game and to spectators.
+    Cursor { x: usize, y: usize },
+
+    /// Throw away the current game and start a new one on the same board.
+    NewGame,
+
+    /// Stop playing.
+    Quit,
+}
+
+/// A message sent by the game to the player.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Event {
+    /// The session the connection is playing in. Sent by the server when a connection
+    /// starts, so the player can come back to the same game after losing the connection.
+    /// Unlike the session, the watch ID can be shared since it only lets others watch.
+    Joined { session: String, watch: String },
+
+    /// The connection is watching the game with the given watch ID and can't play.
+    Watching { watch: String },
+
+    /// What the player can see of the board. Sent when a game starts and after each action.
+    Board(PlayerView),
+
+    /// What happened because of an action.
+    Response(Response),
+
+    /// The action couldn't be done.
+    Error { error: GameError, message: String },
+
+    /// The request couldn't be understood.
+    Invalid { message: String },
+
+    /// The versus match is waiting for more players to join.
+    Waiting { players: usize, needed: usize },
+
+    /// The versus match has started. Players are numbered from 1 in the order they joined.
+    MatchStarted { player: usize, players: usize },
+
+    /// How every player in the versus match is doing. Sent whenever anyone acts.
+    Standings(Vec<Standing>),
+
+    /// The final ranking of the versus match, sent once every player is done.
+    Results(Vec<Standing>),
+
+    /// The players sharing the cooperative game. Sent when someone joins, moves their
+    /// cursor or changes a mark.
+    Partners(Vec<Partner>),
+
+    /// The action was dropped because it clashed with another player in the cooperative
+    /// game.
+    Conflict { x: usize, y: usize, message: String },
+
+    /// A player pointed at a tile. Only sent to spectators.
+    Cursor { player: usize, x: usize, y: usize },
+
+    /// Something that happened to one of the players in a versus match. Only sent to
+    /// spectators, since each player has their own board.
+    Watched { player: usize, event: Box<Event> },
+}
+
+/// A player sharing a cooperative game.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Partner {
+    pub player: usize,
+
+    /// The color the player's cursor and marks are drawn in, as `#rrggbb`.
+    pub color: String,
+
+    /// The tile the player is pointing at, once they have pointed at one.
+    pub cursor: Option<(usize, usize)>,
+
+    /// The tiles the player has marked. Only the player who placed a mark can change it.
+    pub marks: Vec<(usize, usize)>,
+}
+
+/// How a player in a versus match is doing.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Standing {
+    pub player: usize,
+
+    /// The percentage of the safe tiles the player has revealed.
+    pub progress: f32,
+
+    /// Set when the player revealed a mine or gave up.
+    pub hit_mine: bool,
+
+    /// How long the player took to clear the board, in milliseconds.
+    pub time: Option<u64>,
+
+    /// The 3BV of the board per second it took the player to clear it.
+    pub three_bv_per_second: Option<f32>,
+}
@@ -0,0 +1,211 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::view::{PlayerView, VisibleTile};
+
+/// The mines that must be among a set of hidden tiles, as told by a revealed number.
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Constraint {
+    tiles: Vec<(usize, usize)>,
+    mines: usize,
+}
+
+/// A `Solver` finds the tiles that are certainly safe using only the `PlayerView`: the
+/// revealed numbers and the mines the solver has found so far. It assumes each bomb
+/// holds a single mine, so it doesn't solve the multi-mine variant.
+#[derive(Clone, Default, Debug)]
+pub struct Solver {
+    mines: HashSet<(usize, usize)>,
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        Solver::default()
+    }
+
+    /// Returns the hidden tiles the solver has proven to be mines.
+    pub fn mines(&self) -> &HashSet<(usize, usize)> {
+        &self.mines
+    }
+
+    /// Returns the hidden tiles that can't be mines. The mines found along the way are
+    /// remembered. An empty list means the player has to guess.
+    pub fn safe_tiles(&mut self, view: &PlayerView) -> Vec<(usize, usize)> {
+        loop {
+            let constraints = self.constraints(view);
+            let mut safe = HashSet::new();
+            let mut found = false;
+
+            // A number with all its mines found makes the rest of its tiles safe, and a
+            // number with as many tiles as mines makes all of them mines.
+            for constraint in &constraints {
+                found |= self.apply(constraint, &mut safe);
+            }
+
+            // When the tiles of one number are all next to another number, the tiles only
+            // the other number touches hold the difference between their mines.
+            for (index, small) in constraints.iter().enumerate() {
+                for large in constraints.iter().skip(index + 1) {
+                    let (small, large) = match small.tiles.len() <= large.tiles.len() {
+                        true => (small, large),
+                        false => (large, small),
+                    };
+                    if large.mines < small.mines
+                        || !small.tiles.iter().all(|tile| large.tiles.contains(tile))
+                    {
+                        continue;
+                    }
+                    let difference = Constraint {
+                        tiles: large
+                            .tiles
+                            .iter()
+                            .filter(|tile| !small.tiles.contains(tile))
+                            .copied()
+                            .collect(),
+                        mines: large.mines - small.mines,
+                    };
+                    found |= self.apply(&difference, &mut safe);
+                }
+            }
+
+            if !safe.is_empty() || !found {
+                let mut safe: Vec<(usize, usize)> = safe.into_iter().collect();
+                safe.sort_unstable();
+                return safe;
+            }
+        }
+    }
+
+    /// Returns the chance that each hidden tile is a mine. Tiles next to a number use the
+    /// most pessimistic number around them, while the rest share the mines that are left.
+    /// This is an estimate, not an exact count of the possible boards.
+    pub fn probabilities(&self, view: &PlayerView) -> HashMap<(usize, usize), f32> {
+        let mut probabilities = HashMap::new();
+        for constraint in self.constraints(view) {
+            let chance = constraint.mines as f32 / constraint.tiles.len() as f32;
+            for tile in constraint.tiles {
+                let entry = probabilities.entry(tile).or_insert(chance);
+                *entry = entry.max(chance);
+            }
+        }
+
+        let others: Vec<(usize, usize)> = self
+            .unknown_tiles(view)
+            .into_iter()
+            .filter(|tile| !probabilities.contains_key(tile))
+            .collect();
+        let mines_left = view.bombs.saturating_sub(self.mines.len()) as f32;
+        let chance = (mines_left / (others.len() + probabilities.len()).max(1) as f32).min(1.0);
+        probabilities.extend(others.into_iter().map(|tile| (tile, chance)));
+        probabilities
+    }
+
+    /// Returns the hidden tiles that aren't known to be mines.
+    pub fn unknown_tiles(&self, view: &PlayerView) -> Vec<(usize, usize)> {
+        (0..view.rows())
+            .flat_map(|y| (0..view.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| self.is_unknown(view, x, y))
+            .collect()
+    }
+
+    // Returns true if the tile is hidden and not known to be a mine.
+    fn is_unknown(&self, view: &PlayerView, x: usize, y: usize) -> bool {
+        view.tile(x, y).is_hidden() && !self.mines.contains(&(x, y))
+    }
+
+    // Returns what each revealed number says about the unknown tiles around it.
+    fn constraints(&self, view: &PlayerView) -> Vec<Constraint> {
+        let mut constraints = Vec::new();
+        for y in 0..view.rows() {
+            for x in 0..view.width {
+                let number = match view.tile(x, y) {
+                    VisibleTile::Number(number) if number > 0 => number,
+                    _ => continue,
+                };
+                let neighbours = view.neighbours(x, y);
+                let known = neighbours
+                    .iter()
+                    .filter(|tile| self.mines.contains(tile))
+                    .count();
+                let mut tiles: Vec<(usize, usize)> = neighbours
+                    .into_iter()
+                    .filter(|&(nx, ny)| self.is_unknown(view, nx, ny))
+                    .collect();
+                if tiles.is_empty() {
+                    continue;
+                }
+                tiles.sort_unstable();
+                let constraint = Constraint {
+                    tiles,
+                    mines: (number as usize).saturating_sub(known),
+                };
+                if !constraints.contains(&constraint) {
+                    constraints.push(constraint);
+                }
+            }
+        }
+        constraints
+    }
+
+    // Mark the tiles of the constraint when they are all safe or all mines. Returns true if
+    // new mines were found.
+    fn apply(&mut self, constraint: &Constraint, safe: &mut HashSet<(usize, usize)>) -> bool {
+        if constraint.tiles.is_empty() {
+            return false;
+        }
+        if constraint.mines == 0 {
+            safe.extend(constraint.tiles.iter().copied());
+            return false;
+        }
+        if constraint.mines == constraint.tiles.len() {
+            let before = self.mines.len();
+            self.mines.extend(constraint.tiles.iter().copied());
+            return self.mines.len() > before;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Action, Game};
+    use crate::minefield::Minefield;
+
+    // Returns the view of the board drawn as ASCII art after revealing the given tile.
+    fn view(board: &str, (x, y): (usize, usize)) -> PlayerView {
+        let minefield = Minefield::parse(board).unwrap();
+        let mut game = Game::new(1, 1, 0).with_minefield(minefield);
+        game.perform_action(x, y, Action::Reveal).unwrap();
+        assert!(!game.game_over);
+        game.player_view()
+    }
+
+    #[test]
+    fn numbers_with_as_many_tiles_as_mines_are_all_mines() {
+        let view = view("*...\n*...\n....", (3, 2));
+        let mut solver = Solver::new();
+        assert_eq!(solver.safe_tiles(&view), vec![(0, 2)]);
+        assert_eq!(solver.mines(), &HashSet::from([(0, 0), (0, 1)]));
+    }
+
+    #[test]
+    fn numbers_inside_other_numbers_tell_the_difference() {
+        let view = view("*...\n....\n*...", (3, 1));
+        let mut solver = Solver::new();
+        assert_eq!(solver.safe_tiles(&view), vec![(0, 1)]);
+        assert_eq!(solver.mines(), &HashSet::from([(0, 0), (0, 2)]));
+    }
+
+    #[test]
+    fn guesses_have_no_safe_tiles() {
+        let minefield = Minefield::parse("*...\n....\n..*.\n....").unwrap();
+        let view = Game::new(1, 1, 0).with_minefield(minefield).player_view();
+        let mut solver = Solver::new();
+        assert!(solver.safe_tiles(&view).is_empty());
+
+        // Without any numbers, every tile has the same chance of being a mine.
+        let probabilities = solver.probabilities(&view);
+        assert_eq!(probabilities.len(), 16);
+        assert!(probabilities.values().all(|chance| *chance == 2.0 / 16.0));
+    }
+}
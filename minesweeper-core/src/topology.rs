@@ -0,0 +1,328 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The `TileShape` enum describes which sprites should be used to draw the tiles.
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum TileShape {
+    /// Square tiles laid out in a grid.
+    #[default]
+    Square,
+
+    /// Pointy-topped hexagonal tiles.
+    Hex,
+}
+
+/// A `Topology` describes how the tiles of a board are connected to each other and
+/// where they are placed on the screen. Implement it to experiment with new board
+/// variants without touching the game logic.
+///
+/// Positions are measured in tiles, with `(0.0, 0.0)` being the center of the top
+/// left tile and `y` growing downwards.
+pub trait Topology: Send + Sync + 'static {
+    /// Returns the coordinates of all the tiles surrounding the given tile.
+    fn neighbours(&self, x: usize, y: usize, width: usize, height: usize) -> Vec<(usize, usize)>;
+
+    /// Returns the center of the given tile.
+    fn layout(&self, x: usize, y: usize) -> (f32, f32) {
+        (x as f32, y as f32)
+    }
+
+    /// Returns the width and height of the whole board.
+    fn extent(&self, width: usize, height: usize) -> (f32, f32) {
+        (width as f32, height as f32)
+    }
+
+    /// Returns the tile found at the given position, if any.
+    fn tile_at(&self, x: f32, y: f32, width: usize, height: usize) -> Option<(usize, usize)> {
+        let x = x.round();
+        let y = y.round();
+        if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    /// Returns the shape of the tiles.
+    fn shape(&self) -> TileShape {
+        TileShape::Square
+    }
+
+    /// Returns true if the edges of the board are connected to the opposite edges.
+    fn wraps(&self) -> bool {
+        false
+    }
+
+    /// Returns true if the tiles of a board with the given size can be connected. Every
+    /// tile must be a neighbour of each of its neighbours.
+    fn supports(&self, _width: usize, _height: usize) -> bool {
+        true
+    }
+}
+
+/// A `TopologyDescriptor` names one of the built-in topologies, so a board can be sent
+/// along with how its tiles are connected. Other topologies are described by the shape of
+/// their tiles and come back as the built-in topology with that shape.
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct TopologyDescriptor {
+    pub shape: TileShape,
+    pub wrap: bool,
+}
+
+impl TopologyDescriptor {
+    /// Returns the descriptor of the given topology.
+    pub fn of(topology: &dyn Topology) -> TopologyDescriptor {
+        TopologyDescriptor {
+            shape: topology.shape(),
+            wrap: topology.wraps(),
+        }
+    }
+
+    /// Returns the topology the descriptor names.
+    pub fn topology(&self) -> Arc<dyn Topology> {
+        match self.shape {
+            TileShape::Square => Arc::new(SquareTopology { wrap: self.wrap }),
+            TileShape::Hex => Arc::new(HexTopology { wrap: self.wrap }),
+        }
+    }
+}
+
+/// Serializes a shared topology as its `TopologyDescriptor`, for use with
+/// `#[serde(with = "crate::topology::descriptor")]`.
+pub(crate) mod descriptor {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        topology: &Arc<dyn Topology>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        TopologyDescriptor::of(topology.as_ref()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Arc<dyn Topology>, D::Error> {
+        Ok(TopologyDescriptor::deserialize(deserializer)?.topology())
+    }
+}
+
+/// Collect the tiles at the given offsets from `(x, y)`. When wrapping, tiles that fall
+/// off an edge are taken from the opposite edge, otherwise they are skipped.
+fn offset_neighbours(
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    wrap: bool,
+    offsets: &[(isize, isize)],
+) -> Vec<(usize, usize)> {
+    let mut neighbours = Vec::with_capacity(offsets.len());
+    for (dx, dy) in offsets {
+        let mut nx = x as isize + dx;
+        let mut ny = y as isize + dy;
+        if wrap {
+            nx = nx.rem_euclid(width as isize);
+            ny = ny.rem_euclid(height as isize);
+        }
+        if nx < 0 || nx >= width as isize || ny < 0 || ny >= height as isize {
+            continue;
+        }
+
+        // Small wrapping boards can reach the same tile, or the tile itself, more than once.
+        let neighbour = (nx as usize, ny as usize);
+        if neighbour != (x, y) && !neighbours.contains(&neighbour) {
+            neighbours.push(neighbour);
+        }
+    }
+    neighbours
+}
+
+/// The classic grid where each tile has up to eight neighbours.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct SquareTopology {
+    /// Connect the left edge to the right edge and the top edge to the bottom edge.
+    pub wrap: bool,
+}
+
+impl Topology for SquareTopology {
+    fn neighbours(&self, x: usize, y: usize, width: usize, height: usize) -> Vec<(usize, usize)> {
+        const OFFSETS: [(isize, isize); 8] = [
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (-1, 0),
+            (1, 0),
+            (-1, 1),
+            (0, 1),
+            (1, 1),
+        ];
+        offset_neighbours(x, y, width, height, self.wrap, &OFFSETS)
+    }
+
+    fn wraps(&self) -> bool {
+        self.wrap
+    }
+}
+
+/// The vertical distance between two rows of hexagons.
+const HEX_ROW_HEIGHT: f32 = 0.866_025_4;
+
+/// A grid of hexagons where each tile has up to six neighbours. Odd rows are shifted
+/// half a tile to the right.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct HexTopology {
+    /// Connect the left edge to the right edge and the top edge to the bottom edge. The
+    /// rows only line up across the top and bottom edges when the height is even, so
+    /// wrapping boards need an even height.
+    pub wrap: bool,
+}
+
+impl Topology for HexTopology {
+    fn neighbours(&self, x: usize, y: usize, width: usize, height: usize) -> Vec<(usize, usize)> {
+        const EVEN: [(isize, isize); 6] = [(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)];
+        const ODD: [(isize, isize); 6] = [(0, -1), (1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)];
+        match y % 2 {
+            0 => offset_neighbours(x, y, width, height, self.wrap, &EVEN),
+            _ => offset_neighbours(x, y, width, height, self.wrap, &ODD),
+        }
+    }
+
+    fn layout(&self, x: usize, y: usize) -> (f32, f32) {
+        let shift = if y % 2 == 1 { 0.5 } else { 0.0 };
+        (x as f32 + shift, y as f32 * HEX_ROW_HEIGHT)
+    }
+
+    fn extent(&self, width: usize, height: usize) -> (f32, f32) {
+        (width as f32 + 0.5, height as f32 * HEX_ROW_HEIGHT)
+    }
+
+    fn tile_at(&self, x: f32, y: f32, width: usize, height: usize) -> Option<(usize, usize)> {
+        // Check the closest row and the ones around it and pick the nearest center.
+        let row = (y / HEX_ROW_HEIGHT).round() as isize;
+        let mut closest: Option<((usize, usize), f32)> = None;
+        for ty in (row - 1)..=(row + 1) {
+            if ty < 0 || ty >= height as isize {
+                continue;
+            }
+            let shift = if ty % 2 == 1 { 0.5 } else { 0.0 };
+            let tx = (x - shift).round() as isize;
+            if tx < 0 || tx >= width as isize {
+                continue;
+            }
+            let (cx, cy) = self.layout(tx as usize, ty as usize);
+            let distance = (cx - x).powi(2) + (cy - y).powi(2);
+            if closest.is_none_or(|(_, best)| distance < best) {
+                closest = Some(((tx as usize, ty as usize), distance));
+            }
+        }
+
+        // Ignore positions that are further away than the corners of the hexagon.
+        closest
+            .filter(|(_, distance)| *distance <= 1.0 / 3.0)
+            .map(|(tile, _)| tile)
+    }
+
+    fn shape(&self) -> TileShape {
+        TileShape::Hex
+    }
+
+    fn wraps(&self) -> bool {
+        self.wrap
+    }
+
+    // An odd row wrapping onto an even row would see tiles that don't see it back.
+    fn supports(&self, _width: usize, height: usize) -> bool {
+        !self.wrap || height.is_multiple_of(2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Returns the neighbours of the tile, sorted so they can be compared.
+    fn sorted(
+        topology: &dyn Topology,
+        x: usize,
+        y: usize,
+        size: (usize, usize),
+    ) -> Vec<(usize, usize)> {
+        let mut neighbours = topology.neighbours(x, y, size.0, size.1);
+        neighbours.sort_unstable();
+        neighbours
+    }
+
+    // Check that every tile is a neighbour of each of its neighbours.
+    fn assert_symmetric(topology: &dyn Topology, width: usize, height: usize) {
+        for y in 0..height {
+            for x in 0..width {
+                for (nx, ny) in topology.neighbours(x, y, width, height) {
+                    assert!(
+                        topology.neighbours(nx, ny, width, height).contains(&(x, y)),
+                        "({}, {}) is next to ({}, {}) but not the other way around",
+                        x,
+                        y,
+                        nx,
+                        ny
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn square_tiles_have_eight_neighbours() {
+        let square = SquareTopology::default();
+        assert_eq!(sorted(&square, 0, 0, (3, 3)), vec![(0, 1), (1, 0), (1, 1)]);
+        assert_eq!(square.neighbours(1, 1, 3, 3).len(), 8);
+        assert_symmetric(&square, 5, 4);
+    }
+
+    #[test]
+    fn hex_tiles_have_six_neighbours() {
+        let hex = HexTopology::default();
+        assert_eq!(
+            sorted(&hex, 1, 2, (4, 4)),
+            vec![(0, 1), (0, 2), (0, 3), (1, 1), (1, 3), (2, 2)]
+        );
+        assert_eq!(
+            sorted(&hex, 1, 1, (4, 4)),
+            vec![(0, 1), (1, 0), (1, 2), (2, 0), (2, 1), (2, 2)]
+        );
+        assert_symmetric(&hex, 5, 5);
+    }
+
+    #[test]
+    fn hex_positions_find_their_tiles() {
+        let hex = HexTopology::default();
+        for y in 0..4 {
+            for x in 0..4 {
+                let (px, py) = hex.layout(x, y);
+                assert_eq!(hex.tile_at(px, py, 4, 4), Some((x, y)));
+            }
+        }
+        assert_eq!(hex.tile_at(-1.0, 0.0, 4, 4), None);
+    }
+
+    #[test]
+    fn wrapping_connects_the_opposite_edges() {
+        let square = SquareTopology { wrap: true };
+        assert_eq!(square.neighbours(0, 0, 4, 4).len(), 8);
+        assert!(square.neighbours(0, 0, 4, 4).contains(&(3, 3)));
+        assert_symmetric(&square, 4, 3);
+
+        let hex = HexTopology { wrap: true };
+        assert_eq!(hex.neighbours(0, 0, 4, 4).len(), 6);
+        assert!(hex.neighbours(0, 0, 4, 4).contains(&(3, 3)));
+        assert_symmetric(&hex, 5, 4);
+    }
+
+    #[test]
+    fn wrapping_hex_boards_need_an_even_height() {
+        let hex = HexTopology { wrap: true };
+        assert!(hex.supports(5, 4));
+        assert!(!hex.supports(4, 5));
+        assert!(HexTopology::default().supports(4, 5));
+        assert!(SquareTopology { wrap: true }.supports(4, 5));
+    }
+}
@@ -0,0 +1,299 @@
+//! What a player is allowed to know about a game. The `Game` knows where every bomb is, so
+//! anything that plays or draws the game should look at a `PlayerView` instead.
+
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Game, TileMark, layered_neighbours};
+use crate::topology::Topology;
+
+/// What a player can see of a tile.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum VisibleTile {
+    /// A tile that hasn't been revealed or marked.
+    Hidden,
+
+    /// A hidden tile flagged as holding the given number of mines.
+    Flag(u8),
+
+    /// A hidden tile marked with a question mark.
+    Question,
+
+    /// A revealed tile with the number of mines around it.
+    Number(u8),
+
+    /// A bomb holding the given number of mines. Bombs are only shown once the game is over.
+    Mine(u8),
+
+    /// A tile removed from the board by a mask.
+    Void,
+}
+
+impl VisibleTile {
+    /// Returns true if the tile hasn't been revealed, whether or not it's marked.
+    pub fn is_hidden(&self) -> bool {
+        matches!(
+            self,
+            VisibleTile::Hidden | VisibleTile::Flag(_) | VisibleTile::Question
+        )
+    }
+
+    /// Returns the character the tile is written as in text. Counts too big for one digit
+    /// are written as `+`.
+    pub fn glyph(&self) -> char {
+        let digit = |count: u8| char::from_digit(count as u32, 10).unwrap_or('+');
+        match self {
+            VisibleTile::Hidden => '#',
+            VisibleTile::Flag(1) => 'F',
+            VisibleTile::Flag(count) => digit(*count),
+            VisibleTile::Question => '?',
+            VisibleTile::Number(0) => '.',
+            VisibleTile::Number(number) => digit(*number),
+            VisibleTile::Mine(1) => '*',
+            VisibleTile::Mine(mines) => digit(*mines),
+            VisibleTile::Void => ' ',
+        }
+    }
+}
+
+/// The `PlayerView` is the board as the player sees it: the revealed numbers, the marks
+/// and which tiles are still hidden. Bombs are left out until the game is over, when every
+/// tile is shown.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PlayerView {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub bombs: usize,
+
+    /// The number of mines on the board. This is more than the bombs when a bomb can hold
+    /// several mines.
+    pub mines: usize,
+
+    pub game_over: bool,
+    pub game_won: bool,
+
+    /// The rows of the board, with the layers stacked one after the other.
+    pub tiles: Vec<Vec<VisibleTile>>,
+
+    /// How the tiles are connected. Only the built-in topologies survive being sent, as
+    /// they are sent as their `TopologyDescriptor`.
+    #[serde(with = "crate::topology::descriptor")]
+    pub topology: Arc<dyn Topology>,
+}
+
+impl PlayerView {
+    pub fn new(game: &Game) -> PlayerView {
+        Self::build(game, game.game_over)
+    }
+
+    /// Returns what the player can see while others are still playing on the same board.
+    /// Only the bomb that ended the game is shown once it's over, so the rest of the board
+    /// stays hidden.
+    pub fn concealed(game: &Game) -> PlayerView {
+        Self::build(game, false)
+    }
+
+    // Returns the view of the game, showing every tile if asked to.
+    fn build(game: &Game, show_all: bool) -> PlayerView {
+        let tiles = (0..game.rows())
+            .map(|y| {
+                (0..game.width)
+                    .map(|x| match game.tile(x, y) {
+                        tile if tile.void => VisibleTile::Void,
+                        tile if tile.bomb
+                            && (tile.revealed || show_all || game.exploded == Some((x, y))) =>
+                        {
+                            VisibleTile::Mine(tile.mines)
+                        }
+                        tile if tile.revealed || show_all => VisibleTile::Number(tile.number),
+                        tile => match tile.mark {
+                            TileMark::None => VisibleTile::Hidden,
+                            TileMark::Flag(count) => VisibleTile::Flag(count),
+                            TileMark::Question => VisibleTile::Question,
+                        },
+                    })
+                    .collect()
+            })
+            .collect();
+        PlayerView {
+            width: game.width,
+            height: game.height,
+            depth: game.depth,
+            bombs: game.bombs,
+            mines: game
+                .board
+                .iter()
+                .flatten()
+                .map(|tile| tile.mines as usize)
+                .sum(),
+            game_over: game.game_over,
+            game_won: game.game_won,
+            tiles,
+            topology: game.topology.clone(),
+        }
+    }
+
+    /// Returns the number of rows on the board, counting every layer.
+    pub fn rows(&self) -> usize {
+        self.height * self.depth
+    }
+
+    pub fn tile(&self, x: usize, y: usize) -> VisibleTile {
+        self.tiles[y][x]
+    }
+
+    /// Returns true if the given tile has been removed from the board by a mask.
+    pub fn is_void(&self, x: usize, y: usize) -> bool {
+        self.tiles[y % self.height][x] == VisibleTile::Void
+    }
+
+    /// Returns the coordinates of all the tiles surrounding the given tile, the same way
+    /// `Game::neighbours` does.
+    pub fn neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        layered_neighbours(
+            self.topology.as_ref(),
+            (self.width, self.height, self.depth),
+            (x, y),
+            |x, y| self.is_void(x, y),
+        )
+    }
+
+    /// Returns the number of mines that haven't been flagged. This goes negative when
+    /// there are more flags than mines.
+    pub fn mines_left(&self) -> i64 {
+        let flags: i64 = self
+            .tiles
+            .iter()
+            .flatten()
+            .map(|tile| match tile {
+                VisibleTile::Flag(count) => *count as i64,
+                _ => 0,
+            })
+            .sum();
+        self.mines as i64 - flags
+    }
+}
+
+/// Write the board as text that can be pasted into a chat, with a line saying how the game
+/// is going followed by a line per row. Layers are separated by an empty line.
+impl fmt::Display for PlayerView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match (self.game_over, self.game_won) {
+            (true, true) => "won".to_string(),
+            (true, false) => "lost".to_string(),
+            (false, _) => format!("{} left", self.mines_left()),
+        };
+        writeln!(
+            f,
+            "Minesweeper {}x{}, {} mines, {}",
+            self.width, self.height, self.mines, status
+        )?;
+        for (y, row) in self.tiles.iter().enumerate() {
+            if y > 0 && y % self.height == 0 {
+                writeln!(f)?;
+            }
+            let line: String = row.iter().map(|tile| tile.glyph()).collect();
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PlayerView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PlayerView")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("depth", &self.depth)
+            .field("bombs", &self.bombs)
+            .field("mines", &self.mines)
+            .field("game_over", &self.game_over)
+            .field("game_won", &self.game_won)
+            .field("tiles", &self.tiles)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Action;
+    use crate::minefield::Minefield;
+    use crate::topology::{HexTopology, TileShape};
+
+    // Returns true if the view shows a bomb the player hasn't found yet.
+    fn shows_hidden_bombs(game: &Game, view: &PlayerView) -> bool {
+        (0..game.rows())
+            .flat_map(|y| (0..game.width).map(move |x| (x, y)))
+            .any(|(x, y)| game.tile(x, y).bomb && !view.tile(x, y).is_hidden())
+    }
+
+    #[test]
+    fn bombs_stay_hidden_until_the_game_is_over() {
+        for seed in 0..10 {
+            let mut game = Game::new(9, 9, 10).with_seed(seed);
+            while let Some((x, y)) = game.hint() {
+                assert!(!shows_hidden_bombs(&game, &game.player_view()));
+                game.perform_action(x, y, Action::Reveal).unwrap();
+            }
+            assert!(game.game_won);
+        }
+
+        let mut game = Game::new(9, 9, 10).with_seed(1).with_question_marks(true);
+        let (x, y) = (0..81)
+            .map(|index| (index % 9, index / 9))
+            .find(|&(x, y)| game.tile(x, y).bomb)
+            .unwrap();
+        game.perform_action(x, y, Action::Flag).unwrap();
+        assert_eq!(game.player_view().tile(x, y), VisibleTile::Flag(1));
+        game.perform_action(x, y, Action::Flag).unwrap();
+        assert_eq!(game.player_view().tile(x, y), VisibleTile::Question);
+    }
+
+    #[test]
+    fn every_tile_is_shown_once_the_game_is_lost() {
+        let minefield = Minefield::parse("*..\n...\n..*").unwrap();
+        let mut game = Game::new(1, 1, 0).with_minefield(minefield);
+        game.perform_action(0, 0, Action::Reveal).unwrap();
+        let view = game.player_view();
+        assert!(view.game_over && !view.game_won);
+        assert_eq!(view.tile(2, 2), VisibleTile::Mine(1));
+        assert_eq!(view.tile(1, 1), VisibleTile::Number(2));
+        assert_eq!(
+            view.to_string(),
+            "Minesweeper 3x3, 2 mines, lost\n*1.\n121\n.1*\n"
+        );
+    }
+
+    #[test]
+    fn views_keep_their_topology_when_sent() {
+        let topology = Arc::new(HexTopology { wrap: true });
+        let game = Game::new(4, 4, 3).with_topology(topology);
+        let view = game.player_view();
+        let json = serde_json::to_string(&view).unwrap();
+        assert!(!json.contains("Mine"));
+
+        let sent: PlayerView = serde_json::from_str(&json).unwrap();
+        assert_eq!(sent.topology.shape(), TileShape::Hex);
+        assert!(sent.topology.wraps());
+        assert_eq!(sent.tiles, view.tiles);
+        assert_eq!(sent.neighbours(0, 0), view.neighbours(0, 0));
+    }
+
+    #[test]
+    fn concealed_views_only_show_the_bomb_that_was_hit() {
+        let minefield = Minefield::parse("*..\n...\n..*").unwrap();
+        let mut game = Game::new(1, 1, 0).with_minefield(minefield);
+        game.perform_action(2, 0, Action::Reveal).unwrap();
+        game.perform_action(0, 0, Action::Reveal).unwrap();
+        let view = PlayerView::concealed(&game);
+        assert!(view.game_over && !view.game_won);
+        assert_eq!(view.tile(0, 0), VisibleTile::Mine(1));
+        assert_eq!(view.tile(2, 0), VisibleTile::Number(0));
+        assert_eq!(view.tile(2, 2), VisibleTile::Hidden);
+        assert_eq!(view.tile(0, 2), VisibleTile::Hidden);
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use minesweeper_core::{Action, Event, Game, Partner, Request, VisibleTile};
+
+use crate::table::{Spectators, Table};
+
+/// The colors given to the players in the order they join.
+const COLORS: [&str; 8] = [
+    "#8caaee", "#e78284", "#a6d189", "#e5c890", "#ca9ee6", "#81c8be", "#ef9f76", "#f4b8e4",
+];
+
+/// How long a tile belongs to the player that last acted on it. Actions by anyone else on
+/// the tile during this time are dropped, so two players clicking the same tile at once
+/// don't undo each other.
+const CONFLICT_WINDOW: Duration = Duration::from_millis(300);
+
+/// A player sharing the game.
+struct Member {
+    /// Where the events for the player's connection go. It's replaced when the player
+    /// reconnects.
+    outbox: Sender<Event>,
+
+    cursor: Option<(usize, usize)>,
+
+    /// Whether the player asked for a new game while this one is still being played.
+    wants_new_game: bool,
+
+    /// Whether the player quit. They don't get a say in starting a new game anymore.
+    left: bool,
+}
+
+/// A `Coop` is a single game shared by several players. Everyone sees every action, marks
+/// belong to the player that placed them and anyone hitting a mine loses the game for the
+/// whole team.
+pub struct Coop {
+    game: Game,
+
+    members: Vec<Member>,
+
+    /// The player that placed each mark.
+    owners: HashMap<(usize, usize), usize>,
+
+    /// The player that last acted on each tile and when.
+    recent: HashMap<(usize, usize), (usize, Instant)>,
+
+    spectators: Spectators,
+}
+
+impl Coop {
+    pub fn new(game: Game) -> Coop {
+        Coop {
+            game,
+            members: Vec::new(),
+            owners: HashMap::new(),
+            recent: HashMap::new(),
+            spectators: Spectators::default(),
+        }
+    }
+
+    // Do an action on the shared game, unless it clashes with another player.
+    fn act(&mut self, player: usize, x: usize, y: usize, action: Action) {
+        if let Some(message) = self.conflict(player, x, y, action) {
+            self.send(player, Event::Conflict { x, y, message });
+            return;
+        }
+
+        let response = match self.game.perform_action(x, y, action) {
+            Ok(response) => response,
+            Err(error) => {
+                let message = error.to_string();
+                self.send(player, Event::Error { error, message });
+                return;
+            }
+        };
+        self.recent.insert((x, y), (player, Instant::now()));
+
+        // Marks belong to whoever placed them, and go away with the tiles they were on.
+        if action == Action::Flag {
+            self.owners.insert((x, y), player);
+        }
+        let view = self.game.player_view();
+        self.owners.retain(|&(x, y), _| {
+            matches!(
+                view.tile(x, y),
+                VisibleTile::Flag(_) | VisibleTile::Question
+            )
+        });
+
+        self.broadcast(Event::Response(response));
+        self.broadcast(Event::Board(view));
+        self.broadcast(Event::Partners(self.partners()));
+    }
+
+    // Returns why the action can't be done by the player, if another player is in the way.
+    fn conflict(&self, player: usize, x: usize, y: usize, action: Action) -> Option<String> {
+        match self.recent.get(&(x, y)) {
+            Some(&(other, when)) if other != player && when.elapsed() < CONFLICT_WINDOW => {
+                return Some(format!("Player {} just acted on this tile", other + 1));
+            }
+            _ => {}
+        }
+        match self.owners.get(&(x, y)) {
+            Some(&owner) if owner != player && action == Action::Flag => {
+                Some(format!("Only player {} can change this mark", owner + 1))
+            }
+            _ => None,
+        }
+    }
+
+    // Returns the players along with their cursors and marks.
+    fn partners(&self) -> Vec<Partner> {
+        self.members
+            .iter()
+            .enumerate()
+            .map(|(player, member)| {
+                let mut marks: Vec<(usize, usize)> = self
+                    .owners
+                    .iter()
+                    .filter(|&(_, &owner)| owner == player)
+                    .map(|(&tile, _)| tile)
+                    .collect();
+                marks.sort_unstable();
+                Partner {
+                    player: player + 1,
+                    color: COLORS[player % COLORS.len()].to_string(),
+                    cursor: member.cursor,
+                    marks,
+                }
+            })
+            .collect()
+    }
+
+    // Send an event to one player. Players that lost their connection miss it, but are
+    // caught up when they reconnect.
+    fn send(&self, player: usize, event: Event) {
+        let _ = self.members[player].outbox.send(event);
+    }
+
+    // Send an event to every player and the spectators.
+    fn broadcast(&mut self, event: Event) {
+        self.spectators.send(&event);
+        for player in 0..self.members.len() {
+            self.send(player, event.clone());
+        }
+    }
+}
+
+impl Table for Coop {
+    // Players can join as long as someone is still at the table, even after a loss, so the
+    // team stays together for the next game.
+    fn is_open(&self) -> bool {
+        self.members.is_empty() || self.members.iter().any(|member| !member.left)
+    }
+
+    fn add(&mut self, outbox: Sender<Event>) -> usize {
+        self.members.push(Member {
+            outbox,
+            cursor: None,
+            wants_new_game: false,
+            left: false,
+        });
+        let player = self.members.len() - 1;
+        self.send(player, Event::Board(self.game.player_view()));
+        self.broadcast(Event::Partners(self.partners()));
+        player
+    }
+
+    fn connect(&mut self, player: usize, outbox: Sender<Event>) {
+        self.members[player].outbox = outbox;
+        self.send(player, Event::Board(self.game.player_view()));
+        self.send(player, Event::Partners(self.partners()));
+    }
+
+    fn perform(&mut self, player: usize, request: Request) {
+        match request {
+            Request::Action { x, y, action } => self.act(player, x, y, action),
+            Request::Cursor { x, y } => {
+                if x >= self.game.width || y >= self.game.rows() {
+                    return;
+                }
+                self.members[player].cursor = Some((x, y));
+                self.broadcast(Event::Partners(self.partners()));
+            }
+            // The board is shared, so it's only replaced once the game is over or everyone
+            // asks for a new one.
+            Request::NewGame => {
+                self.members[player].wants_new_game = true;
+                let everyone = self
+                    .members
+                    .iter()
+                    .all(|member| member.wants_new_game || member.left);
+                if !self.game.game_over && !everyone {
+                    let message =
+                        "A new game starts once everyone asks for one or the game is over"
+                            .to_string();
+                    self.send(player, Event::Invalid { message });
+                    return;
+                }
+                for member in &mut self.members {
+                    member.wants_new_game = false;
+                }
+                self.game.reset();
+                self.owners.clear();
+                self.recent.clear();
+                self.broadcast(Event::Board(self.game.player_view()));
+                self.broadcast(Event::Partners(self.partners()));
+            }
+            // The player's marks stay on the board for the rest of the team.
+            Request::Quit => self.members[player].left = true,
+        }
+    }
+
+    fn watch(&mut self, outbox: Sender<Event>) {
+        let _ = outbox.send(Event::Board(self.game.player_view()));
+        let _ = outbox.send(Event::Partners(self.partners()));
+        self.spectators.add(outbox);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{self, Receiver};
+    use std::thread;
+
+    use minesweeper_core::Minefield;
+
+    use super::*;
+
+    // Returns a shared game on a small board with two players at it.
+    fn coop() -> (Coop, Receiver<Event>, Receiver<Event>) {
+        let minefield = Minefield::parse("...\n...\n*.*\n.*.").unwrap();
+        let mut table = Coop::new(Game::new(1, 1, 0).with_minefield(minefield));
+        let (first, first_inbox) = mpsc::channel();
+        let (second, second_inbox) = mpsc::channel();
+        table.add(first);
+        table.add(second);
+        (table, first_inbox, second_inbox)
+    }
+
+    fn act(table: &mut Coop, player: usize, x: usize, y: usize, action: Action) {
+        table.perform(player, Request::Action { x, y, action });
+    }
+
+    // Returns the message of the last conflict in the inbox, if any.
+    fn conflict(inbox: &Receiver<Event>) -> Option<String> {
+        inbox.try_iter().fold(None, |last, event| match event {
+            Event::Conflict { message, .. } => Some(message),
+            _ => last,
+        })
+    }
+
+    #[test]
+    fn tiles_belong_to_the_last_player_for_a_moment() {
+        let (mut table, _, second_inbox) = coop();
+        act(&mut table, 0, 1, 0, Action::Reveal);
+        act(&mut table, 1, 1, 0, Action::Chord);
+        assert_eq!(
+            conflict(&second_inbox).as_deref(),
+            Some("Player 1 just acted on this tile")
+        );
+
+        thread::sleep(CONFLICT_WINDOW);
+        act(&mut table, 1, 1, 0, Action::Chord);
+        assert_eq!(conflict(&second_inbox), None);
+    }
+
+    #[test]
+    fn marks_belong_to_whoever_placed_them() {
+        let (mut table, first_inbox, second_inbox) = coop();
+        act(&mut table, 0, 0, 2, Action::Flag);
+        let partners = first_inbox
+            .try_iter()
+            .fold(None, |last, event| match event {
+                Event::Partners(partners) => Some(partners),
+                _ => last,
+            });
+        assert_eq!(partners.unwrap()[0].marks, vec![(0, 2)]);
+
+        thread::sleep(CONFLICT_WINDOW);
+        act(&mut table, 1, 0, 2, Action::Flag);
+        assert_eq!(
+            conflict(&second_inbox).as_deref(),
+            Some("Only player 1 can change this mark")
+        );
+        act(&mut table, 0, 0, 2, Action::Flag);
+        assert_eq!(conflict(&first_inbox), None);
+        assert!(table.owners.is_empty());
+    }
+
+    #[test]
+    fn new_games_need_everyone_until_the_game_is_over() {
+        let (mut table, first_inbox, _) = coop();
+        act(&mut table, 0, 1, 0, Action::Reveal);
+        table.perform(0, Request::NewGame);
+        assert!(matches!(
+            first_inbox.try_iter().last(),
+            Some(Event::Invalid { .. })
+        ));
+        assert!(table.game.tile(1, 0).revealed);
+
+        table.perform(1, Request::NewGame);
+        assert!(!table.game.tile(1, 0).revealed);
+
+        // Once the game is lost anyone can start the next one, and the team stays
+        // together in the meantime.
+        act(&mut table, 1, 0, 2, Action::Reveal);
+        assert!(table.game.game_over && table.is_open());
+        table.perform(0, Request::NewGame);
+        assert!(!table.game.game_over);
+    }
+
+    #[test]
+    fn players_who_quit_do_not_hold_up_a_new_game() {
+        let (mut table, _, _) = coop();
+        act(&mut table, 0, 1, 0, Action::Reveal);
+        table.perform(1, Request::Quit);
+        assert!(table.is_open());
+        table.perform(0, Request::NewGame);
+        assert!(!table.game.tile(1, 0).revealed);
+
+        table.perform(0, Request::Quit);
+        assert!(!table.is_open());
+    }
+}
@@ -0,0 +1,172 @@
+mod coop;
+mod server;
+mod solo;
+mod table;
+mod versus;
+
+use std::io;
+use std::net::TcpListener;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+use clap::{CommandFactory, Parser, error::ErrorKind};
+use minesweeper_core::{
+    FirstClick, Game, HexTopology, Minefield, SquareTopology, Topology, Variant,
+};
+use server::{PRUNE_INTERVAL, Server};
+
+#[derive(Parser, Debug)]
+#[clap(author = "The Marshians", version = "0.1.0", about = "Host Minesweeper games over WebSocket!", long_about = None)]
+pub struct Args {
+    #[clap(long, default_value = "127.0.0.1:9001")]
+    /// The address to listen on. Use `0.0.0.0:9001` to let other machines on the network
+    /// join.
+    address: String,
+
+    #[clap(long, default_value_t = 16)]
+    /// The number of tiles in each row.
+    width: usize,
+
+    #[clap(long, default_value_t = 16)]
+    /// The number of rows in each layer.
+    height: usize,
+
+    #[clap(long, default_value_t = 1)]
+    /// The number of layers stacked on top of each other.
+    depth: usize,
+
+    #[clap(long, default_value_t = 40)]
+    /// The number of bombs on the board.
+    bombs: usize,
+
+    #[clap(long)]
+    /// Use hexagonal tiles with six neighbours.
+    hex: bool,
+
+    #[clap(short, long)]
+    /// Connect the edges of the board to the opposite edges.
+    wrap: bool,
+
+    #[clap(long, value_parser = load_minefield, conflicts_with = "depth")]
+    /// Play a saved board in every session. `.mbf` files are read as MBF and everything
+    /// else as ASCII art where `*` is a mine and layers are separated by an empty line. The
+    /// board replaces the width, height, depth and bombs.
+    minefield: Option<Minefield>,
+
+    #[clap(long)]
+    /// Let each bomb hold between one and three mines.
+    multi_mine: bool,
+
+    #[clap(short, long)]
+    /// Cycle through a question mark after the flag when marking tiles.
+    question_marks: bool,
+
+    #[clap(long, value_enum, default_value_t)]
+    /// How the bombs are kept away from the first revealed tile.
+    first_click: FirstClick,
+
+    #[clap(long)]
+    /// Place the bombs the same way in every session.
+    seed: Option<u64>,
+
+    #[clap(long, default_value_t = 2)]
+    /// The number of players a versus match waits for before it starts. Players join a
+    /// match by connecting to `ws://<address>/versus`.
+    players: usize,
+
+    #[clap(long, default_value_t = 3.0)]
+    /// How many seconds spectators of a versus match are kept behind the players, so they
+    /// can't help anyone. Spectators watch a game by connecting to
+    /// `ws://<address>/watch/<watch ID>`.
+    spectator_delay: f32,
+
+    #[clap(long, default_value_t = 300)]
+    /// How many seconds a session is kept after its player loses their connection, so they
+    /// can reconnect to it. The player gives up their seat after that.
+    resume_timeout: u64,
+}
+
+impl Args {
+    /// Returns a new game with the board the flags describe.
+    pub fn new_game(&self) -> Game {
+        let topology: Arc<dyn Topology> = match self.hex {
+            true => Arc::new(HexTopology { wrap: self.wrap }),
+            false => Arc::new(SquareTopology { wrap: self.wrap }),
+        };
+        let mut game = Game::new(self.width, self.height, self.bombs)
+            .with_depth(self.depth)
+            .with_topology(topology)
+            .with_question_marks(self.question_marks)
+            .with_first_click(self.first_click);
+        if self.multi_mine {
+            game = game.with_variant(Variant::MultiMine);
+        }
+        if let Some(minefield) = &self.minefield {
+            game = game.with_minefield(minefield.clone());
+        }
+        if let Some(seed) = self.seed {
+            game = game.with_seed(seed);
+        }
+        game
+    }
+}
+
+// Load the saved board given on the command line.
+fn load_minefield(path: &str) -> Result<Minefield, String> {
+    Minefield::load(Path::new(path)).map_err(|err| err.to_string())
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+
+    // Make sure there is room for all the bombs.
+    let game = args.new_game();
+    if game.bombs >= game.tile_count() {
+        let message = "there must be fewer bombs than tiles";
+        Args::command()
+            .error(ErrorKind::ValueValidation, message)
+            .exit();
+    }
+
+    // Make sure the edges of the board line up where they wrap.
+    if !game.topology.supports(game.width, game.height) {
+        let message = "wrapping hex boards need an even height";
+        Args::command()
+            .error(ErrorKind::ValueValidation, message)
+            .exit();
+    }
+
+    let listener = TcpListener::bind(&args.address)?;
+    println!("Listening on ws://{}", listener.local_addr()?);
+
+    // Each connection gets its own thread. The games are shared through the server so a
+    // player can pick up their game on a new connection.
+    let server = Arc::new(Server::new(args));
+
+    // Seats are given up on a timer, so matches finish even when nobody new connects.
+    let pruner = server.clone();
+    thread::spawn(move || {
+        loop {
+            thread::sleep(PRUNE_INTERVAL);
+            pruner.prune();
+        }
+    });
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Unable to accept a connection: {}", err);
+                continue;
+            }
+        };
+        let server = server.clone();
+        thread::spawn(move || {
+            if let Err(err) = server.handle(stream) {
+                eprintln!("Connection closed: {}", err);
+            }
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,537 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use minesweeper_core::{Event, Request};
+use thiserror::Error;
+use tungstenite::error::ProtocolError;
+use tungstenite::handshake::server::{Request as HttpRequest, Response as HttpResponse};
+use tungstenite::{Message, WebSocket};
+
+use crate::Args;
+use crate::coop::Coop;
+use crate::solo::Solo;
+use crate::table::Table;
+use crate::versus::Match;
+
+/// How long a connection waits for its player before passing on the events from the other
+/// players.
+const POLL: Duration = Duration::from_millis(50);
+
+/// The path that joins a versus match instead of starting a game alone.
+const VERSUS_PATH: &str = "versus";
+
+/// The path that joins the cooperative game instead of starting a game alone.
+const COOP_PATH: &str = "coop";
+
+/// The start of the path that watches a game, followed by its watch ID.
+const WATCH_PATH: &str = "watch/";
+
+/// How often the server looks for sessions that ran out and tables nobody is at.
+pub const PRUNE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A table shared by the connections of its players and spectators.
+type SharedTable = Arc<Mutex<dyn Table>>;
+
+#[derive(Error, Debug)]
+pub enum ServerError {
+    #[error("handshake failed: {0}")]
+    Handshake(String),
+    #[error(transparent)]
+    WebSocket(Box<tungstenite::Error>),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl From<tungstenite::Error> for ServerError {
+    fn from(err: tungstenite::Error) -> ServerError {
+        ServerError::WebSocket(Box::new(err))
+    }
+}
+
+/// A player's seat at a table.
+#[derive(Clone)]
+struct Session {
+    table: SharedTable,
+    player: usize,
+
+    /// The ID others use to watch the table.
+    watch: String,
+
+    /// When the player lost their connection, if they aren't connected.
+    left: Option<Instant>,
+
+    /// Counts the connections made to the session, so a connection that closes after the
+    /// player reconnected elsewhere can tell it's no longer the current one.
+    connection: u64,
+}
+
+/// What came in on a connection.
+enum Incoming {
+    Text(String),
+
+    /// Nothing came in while waiting, or it wasn't something that needs an answer.
+    Nothing,
+
+    Closed,
+}
+
+/// The events waiting to be shown to a spectator.
+struct Delayed {
+    delay: Duration,
+
+    /// The events in the order they came in, along with when they are due.
+    pending: VecDeque<(Instant, Event)>,
+}
+
+impl Delayed {
+    fn new(delay: Duration) -> Delayed {
+        Delayed {
+            delay,
+            pending: VecDeque::new(),
+        }
+    }
+
+    // Hold back the events that came in at the given time.
+    fn push(&mut self, events: impl IntoIterator<Item = Event>, now: Instant) {
+        let due = now + self.delay;
+        self.pending
+            .extend(events.into_iter().map(|event| (due, event)));
+    }
+
+    // Returns the events that are due at the given time.
+    fn due(&mut self, now: Instant) -> Vec<Event> {
+        let mut events = Vec::new();
+        while self.pending.front().is_some_and(|(due, _)| *due <= now) {
+            if let Some((_, event)) = self.pending.pop_front() {
+                events.push(event);
+            }
+        }
+        events
+    }
+}
+
+/// The `Server` holds the games being played. Games are only ever changed here and
+/// players are sent what they can see of the board, so the bombs never leave the server
+/// until the game is over.
+pub struct Server {
+    /// The flags used to create new games.
+    args: Args,
+
+    /// The sessions by ID. Sessions are kept for a while after the connection is lost so
+    /// the player can reconnect to them.
+    sessions: Mutex<HashMap<String, Session>>,
+
+    /// The tables by watch ID. Tables are dropped once nobody is playing at or watching
+    /// them.
+    tables: Mutex<HashMap<String, SharedTable>>,
+
+    /// The versus match waiting for players, if any.
+    open_match: Mutex<Option<(String, SharedTable)>>,
+
+    /// The cooperative game new players join, if any.
+    open_coop: Mutex<Option<(String, SharedTable)>>,
+}
+
+impl Server {
+    pub fn new(args: Args) -> Server {
+        Server {
+            args,
+            sessions: Mutex::new(HashMap::new()),
+            tables: Mutex::new(HashMap::new()),
+            open_match: Mutex::new(None),
+            open_coop: Mutex::new(None),
+        }
+    }
+
+    /// Serve the connection on the other end of the stream until it closes. The path of
+    /// the WebSocket URL picks what to do: `ws://host:port/<session>` picks up a session
+    /// where the player left off, `ws://host:port/versus` joins a versus match,
+    /// `ws://host:port/coop` joins the cooperative game, `ws://host:port/watch/<watch>`
+    /// watches a game without playing and any other path starts a new game alone.
+    pub fn handle(&self, stream: TcpStream) -> Result<(), ServerError> {
+        let mut path = String::new();
+        // The error response type belongs to tungstenite, so its size can't be helped.
+        #[allow(clippy::result_large_err)]
+        let callback = |request: &HttpRequest, response: HttpResponse| {
+            path = request.uri().path().to_string();
+            Ok(response)
+        };
+        let mut socket = tungstenite::accept_hdr(stream, callback)
+            .map_err(|err| ServerError::Handshake(err.to_string()))?;
+
+        // Events can come from other connections, so they are queued and sent whenever the
+        // other end is quiet.
+        let (outbox, inbox) = mpsc::channel();
+        socket.get_ref().set_read_timeout(Some(POLL))?;
+
+        let path = path.trim_start_matches('/');
+        match path.strip_prefix(WATCH_PATH) {
+            Some(watch) => self.spectate(&mut socket, watch, outbox, inbox),
+            None => self.play(&mut socket, path, outbox, inbox),
+        }
+    }
+
+    // Pass the player's requests to their table until they leave. Players that lose their
+    // connection keep their session until the resume timeout runs out.
+    fn play(
+        &self,
+        socket: &mut WebSocket<TcpStream>,
+        path: &str,
+        outbox: Sender<Event>,
+        inbox: Receiver<Event>,
+    ) -> Result<(), ServerError> {
+        let (id, session) = self.join(path, outbox);
+        let result = self.serve(socket, &id, &session, inbox);
+        self.disconnect(&id, session.connection);
+        result
+    }
+
+    // Start the resume timeout of the session when its connection closes, unless the
+    // player already reconnected on a newer connection.
+    fn disconnect(&self, id: &str, connection: u64) {
+        match lock(&self.sessions).get_mut(id) {
+            Some(session) if session.connection == connection => {
+                session.left = Some(Instant::now());
+            }
+            _ => {}
+        }
+    }
+
+    // Pass the player's requests to their table until they quit or the connection closes.
+    fn serve(
+        &self,
+        socket: &mut WebSocket<TcpStream>,
+        id: &str,
+        session: &Session,
+        inbox: Receiver<Event>,
+    ) -> Result<(), ServerError> {
+        loop {
+            for event in inbox.try_iter() {
+                send(socket, &event)?;
+            }
+            let text = match receive(socket)? {
+                Incoming::Text(text) => text,
+                Incoming::Nothing => continue,
+                Incoming::Closed => return Ok(()),
+            };
+            let request = match serde_json::from_str::<Request>(&text) {
+                Ok(request) => request,
+                Err(err) => {
+                    let message = err.to_string();
+                    send(socket, &Event::Invalid { message })?;
+                    continue;
+                }
+            };
+
+            let quit = request == Request::Quit;
+            lock(&session.table).perform(session.player, request);
+            if quit {
+                lock(&self.sessions).remove(id);
+                for event in inbox.try_iter() {
+                    send(socket, &event)?;
+                }
+                socket.close(None)?;
+                return Ok(());
+            }
+        }
+    }
+
+    // Pass the events of the table with the given watch ID on to a spectator until they
+    // leave. Competitive games are shown a little late, so spectators can't help anyone.
+    fn spectate(
+        &self,
+        socket: &mut WebSocket<TcpStream>,
+        watch: &str,
+        outbox: Sender<Event>,
+        inbox: Receiver<Event>,
+    ) -> Result<(), ServerError> {
+        let table = lock(&self.tables).get(watch).cloned();
+        let table = match table {
+            Some(table) => table,
+            None => {
+                let message = "There is no game to watch with that ID".to_string();
+                send(socket, &Event::Invalid { message })?;
+                socket.close(None)?;
+                return Ok(());
+            }
+        };
+        let _ = outbox.send(Event::Watching {
+            watch: watch.to_string(),
+        });
+        let mut delayed = {
+            let mut table = lock(&table);
+            table.watch(outbox);
+            Delayed::new(self.spectator_delay(&*table))
+        };
+
+        loop {
+            let now = Instant::now();
+            delayed.push(inbox.try_iter(), now);
+            for event in delayed.due(now) {
+                send(socket, &event)?;
+            }
+            match receive(socket)? {
+                Incoming::Text(_) => {
+                    let message = "Spectators can't play".to_string();
+                    send(socket, &Event::Invalid { message })?;
+                }
+                Incoming::Nothing => {}
+                Incoming::Closed => return Ok(()),
+            }
+        }
+    }
+
+    // Returns how far behind the players the spectators of the table are kept.
+    fn spectator_delay(&self, table: &dyn Table) -> Duration {
+        match table.is_competitive() {
+            true => Duration::from_secs_f32(self.args.spectator_delay.max(0.0)),
+            false => Duration::ZERO,
+        }
+    }
+
+    // Returns the session at the given path, or starts a new one if there is no such
+    // session. The player is sent the session ID and what they need to start playing.
+    fn join(&self, path: &str, outbox: Sender<Event>) -> (String, Session) {
+        let mut sessions = lock(&self.sessions);
+        if let Some(session) = sessions.get_mut(path) {
+            let _ = outbox.send(Event::Joined {
+                session: path.to_string(),
+                watch: session.watch.clone(),
+            });
+            session.left = None;
+            session.connection += 1;
+            lock(&session.table).connect(session.player, outbox);
+            return (path.to_string(), session.clone());
+        }
+
+        let (watch, table) = match path {
+            // Every player in a match gets a copy of the same seeded board.
+            VERSUS_PATH => self.open_table(&self.open_match, || {
+                let seed = self.args.seed.unwrap_or_else(rand::random);
+                let game = self.args.new_game().with_seed(seed);
+                Match::new(game, self.args.players)
+            }),
+            COOP_PATH => self.open_table(&self.open_coop, || Coop::new(self.args.new_game())),
+            _ => self.add_table(Solo::new(self.args.new_game())),
+        };
+        let id = new_id(&sessions);
+        let _ = outbox.send(Event::Joined {
+            session: id.clone(),
+            watch: watch.clone(),
+        });
+        let player = lock(&table).add(outbox);
+        let session = Session {
+            table,
+            player,
+            watch,
+            left: None,
+            connection: 0,
+        };
+        sessions.insert(id.clone(), session.clone());
+        (id, session)
+    }
+
+    /// Give up the seats of the players that have been gone longer than the resume
+    /// timeout, and drop the tables nobody is playing at or watching anymore. Call it every
+    /// `PRUNE_INTERVAL`.
+    pub fn prune(&self) {
+        let timeout = Duration::from_secs(self.args.resume_timeout);
+        let mut expired = Vec::new();
+        lock(&self.sessions).retain(|_, session| match session.left {
+            Some(left) if left.elapsed() > timeout => {
+                expired.push(session.clone());
+                false
+            }
+            _ => true,
+        });
+        for session in expired {
+            lock(&session.table).perform(session.player, Request::Quit);
+        }
+
+        // Sessions, spectators and the open slots hold on to their tables, so a table only
+        // the map holds has nobody left.
+        lock(&self.tables).retain(|_, table| Arc::strong_count(table) > 1);
+    }
+
+    // Returns the table in the slot if it has room for another player, or puts a new one
+    // there.
+    fn open_table<T: Table + 'static>(
+        &self,
+        slot: &Mutex<Option<(String, SharedTable)>>,
+        create: impl FnOnce() -> T,
+    ) -> (String, SharedTable) {
+        let mut slot = lock(slot);
+        match slot.as_ref() {
+            Some((watch, table)) if lock(table).is_open() => {
+                return (watch.clone(), table.clone());
+            }
+            _ => {}
+        }
+        let (watch, table) = self.add_table(create());
+        *slot = Some((watch.clone(), table.clone()));
+        (watch, table)
+    }
+
+    // Share the table and give it a watch ID.
+    fn add_table<T: Table + 'static>(&self, table: T) -> (String, SharedTable) {
+        let table: SharedTable = Arc::new(Mutex::new(table));
+        let mut tables = lock(&self.tables);
+        let watch = new_id(&tables);
+        tables.insert(watch.clone(), table.clone());
+        (watch, table)
+    }
+}
+
+// Returns a random ID that isn't taken yet.
+fn new_id<T>(taken: &HashMap<String, T>) -> String {
+    loop {
+        let id = format!("{:016x}", rand::random::<u64>());
+        if !taken.contains_key(&id) {
+            return id;
+        }
+    }
+}
+
+// Read the next message on the connection, giving up after the poll time.
+fn receive(socket: &mut WebSocket<TcpStream>) -> Result<Incoming, ServerError> {
+    match socket.read() {
+        Ok(Message::Text(text)) => Ok(Incoming::Text(text.to_string())),
+        Ok(Message::Close(_)) => Ok(Incoming::Closed),
+        Ok(_) => Ok(Incoming::Nothing),
+        Err(tungstenite::Error::Io(err))
+            if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+        {
+            Ok(Incoming::Nothing)
+        }
+        // Players that drop their connection can come back to the session later.
+        Err(
+            tungstenite::Error::ConnectionClosed
+            | tungstenite::Error::AlreadyClosed
+            | tungstenite::Error::Protocol(ProtocolError::ResetWithoutClosingHandshake),
+        ) => Ok(Incoming::Closed),
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Lock the mutex, carrying on if another connection panicked while holding it.
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// Write the event as a JSON text message.
+fn send(socket: &mut WebSocket<TcpStream>, event: &Event) -> Result<(), ServerError> {
+    socket.send(Message::text(serde_json::to_string(event)?))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use clap::Parser;
+
+    use super::*;
+
+    // Returns a server that keeps the sessions of lost connections for the given time.
+    fn server(resume_timeout: &str) -> Server {
+        let args = ["minesweeper-server", "--resume-timeout", resume_timeout];
+        Server::new(Args::parse_from(args))
+    }
+
+    // Wait long enough for a session with no resume timeout to run out.
+    fn expire() {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    #[test]
+    fn sessions_resume_at_their_seat() {
+        let server = server("300");
+        let (id, first) = server.join("", mpsc::channel().0);
+        let (outbox, inbox) = mpsc::channel();
+        let (same, second) = server.join(&id, outbox);
+        assert_eq!(same, id);
+        assert!(Arc::ptr_eq(&first.table, &second.table));
+        assert_eq!(second.player, first.player);
+        assert!(matches!(
+            inbox.try_iter().next(),
+            Some(Event::Joined { session, .. }) if session == id
+        ));
+    }
+
+    #[test]
+    fn replaced_connections_leave_the_session_alone() {
+        let server = server("300");
+        let (id, first) = server.join("", mpsc::channel().0);
+        let (_, second) = server.join(&id, mpsc::channel().0);
+        server.disconnect(&id, first.connection);
+        assert_eq!(lock(&server.sessions)[&id].left, None);
+        server.disconnect(&id, second.connection);
+        assert!(lock(&server.sessions)[&id].left.is_some());
+    }
+
+    #[test]
+    fn abandoned_sessions_expire_and_their_tables_go() {
+        let server = server("0");
+        let (gone, session) = server.join("", mpsc::channel().0);
+        let (kept, _) = server.join("", mpsc::channel().0);
+        server.disconnect(&gone, session.connection);
+        drop(session);
+        expire();
+        server.prune();
+
+        let sessions = lock(&server.sessions);
+        assert!(!sessions.contains_key(&gone) && sessions.contains_key(&kept));
+        assert_eq!(lock(&server.tables).len(), 1);
+    }
+
+    #[test]
+    fn expired_players_forfeit_their_match() {
+        let server = server("0");
+        let (outbox, inbox) = mpsc::channel();
+        let (_, stayed) = server.join(VERSUS_PATH, outbox);
+        let (gone, left) = server.join(VERSUS_PATH, mpsc::channel().0);
+        assert!(Arc::ptr_eq(&stayed.table, &left.table));
+        server.disconnect(&gone, left.connection);
+        expire();
+        server.prune();
+
+        let standings = inbox.try_iter().fold(None, |last, event| match event {
+            Event::Standings(standings) => Some(standings),
+            _ => last,
+        });
+        assert!(standings.unwrap()[1].hit_mine);
+    }
+
+    #[test]
+    fn only_competitive_tables_are_watched_late() {
+        let args = ["minesweeper-server", "--spectator-delay", "2.5"];
+        let server = Server::new(Args::parse_from(args));
+        let versus = Match::new(server.args.new_game(), 2);
+        let coop = Coop::new(server.args.new_game());
+        assert_eq!(server.spectator_delay(&versus), Duration::from_millis(2500));
+        assert_eq!(server.spectator_delay(&coop), Duration::ZERO);
+    }
+
+    #[test]
+    fn delayed_events_are_sent_in_order_once_due() {
+        let start = Instant::now();
+        let mut delayed = Delayed::new(Duration::from_secs(3));
+        delayed.push([Event::Standings(Vec::new())], start);
+        let later = start + Duration::from_secs(1);
+        delayed.push([Event::Results(Vec::new())], later);
+
+        assert!(delayed.due(start + Duration::from_secs(2)).is_empty());
+        let due = delayed.due(start + Duration::from_secs(3));
+        assert!(matches!(due.as_slice(), [Event::Standings(_)]));
+        let due = delayed.due(later + Duration::from_secs(3));
+        assert!(matches!(due.as_slice(), [Event::Results(_)]));
+    }
+}
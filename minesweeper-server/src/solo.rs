@@ -0,0 +1,80 @@
+use std::sync::mpsc::Sender;
+
+use minesweeper_core::{Event, Game, Request};
+
+use crate::table::{Spectators, Table};
+
+/// A `Solo` table is a game played alone.
+pub struct Solo {
+    game: Game,
+
+    /// Where the events for the player's connection go, once they have sat down.
+    outbox: Option<Sender<Event>>,
+
+    spectators: Spectators,
+}
+
+impl Solo {
+    pub fn new(game: Game) -> Solo {
+        Solo {
+            game,
+            outbox: None,
+            spectators: Spectators::default(),
+        }
+    }
+
+    // Send an event to the player and the spectators.
+    fn show(&mut self, event: Event) {
+        self.spectators.send(&event);
+        self.send(event);
+    }
+
+    // Send an event to the player. A player that lost their connection misses it, but is
+    // caught up when they reconnect.
+    fn send(&self, event: Event) {
+        if let Some(outbox) = &self.outbox {
+            let _ = outbox.send(event);
+        }
+    }
+}
+
+impl Table for Solo {
+    fn is_open(&self) -> bool {
+        self.outbox.is_none()
+    }
+
+    fn add(&mut self, outbox: Sender<Event>) -> usize {
+        self.connect(0, outbox);
+        0
+    }
+
+    fn connect(&mut self, _player: usize, outbox: Sender<Event>) {
+        self.outbox = Some(outbox);
+        self.send(Event::Board(self.game.player_view()));
+    }
+
+    fn perform(&mut self, _player: usize, request: Request) {
+        match request {
+            Request::Action { x, y, action } => match self.game.perform_action(x, y, action) {
+                Ok(response) => self.show(Event::Response(response)),
+                Err(error) => {
+                    let message = error.to_string();
+                    self.send(Event::Error { error, message });
+                    return;
+                }
+            },
+            Request::Cursor { x, y } => {
+                self.spectators.send(&Event::Cursor { player: 1, x, y });
+                return;
+            }
+            Request::NewGame => self.game.reset(),
+            Request::Quit => return,
+        }
+        self.show(Event::Board(self.game.player_view()));
+    }
+
+    fn watch(&mut self, outbox: Sender<Event>) {
+        let _ = outbox.send(Event::Board(self.game.player_view()));
+        self.spectators.add(outbox);
+    }
+}
@@ -0,0 +1,49 @@
+use std::sync::mpsc::Sender;
+
+use minesweeper_core::{Event, Request};
+
+/// A `Table` is a game hosted by the server, with seats for the players and room for
+/// anyone who wants to watch. Events for the players and spectators go to the outboxes
+/// of their connections.
+pub trait Table: Send {
+    /// Returns true if new players can still take a seat.
+    fn is_open(&self) -> bool;
+
+    /// Returns true if the players compete against each other. Spectators are kept a little
+    /// behind these games so they can't help anyone.
+    fn is_competitive(&self) -> bool {
+        false
+    }
+
+    /// Seat a new player and return their seat.
+    fn add(&mut self, outbox: Sender<Event>) -> usize;
+
+    /// Send the events of a player that reconnected to their new connection, and catch
+    /// them up on the game.
+    fn connect(&mut self, player: usize, outbox: Sender<Event>);
+
+    /// Do what the player asked.
+    fn perform(&mut self, player: usize, request: Request);
+
+    /// Let someone watch the game without playing, catching them up on it first.
+    fn watch(&mut self, outbox: Sender<Event>);
+}
+
+/// The `Spectators` watching a table. Spectators that have left are dropped the next time
+/// something is sent.
+#[derive(Default)]
+pub struct Spectators {
+    outboxes: Vec<Sender<Event>>,
+}
+
+impl Spectators {
+    pub fn add(&mut self, outbox: Sender<Event>) {
+        self.outboxes.push(outbox);
+    }
+
+    /// Send the event to every spectator.
+    pub fn send(&mut self, event: &Event) {
+        self.outboxes
+            .retain(|outbox| outbox.send(event.clone()).is_ok());
+    }
+}
@@ -0,0 +1,474 @@
+use std::cmp::Ordering;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use minesweeper_core::{Action, Event, Game, PlayerView, Request, Standing};
+
+use crate::table::{Spectators, Table};
+
+/// A player racing in a versus match.
+struct Player {
+    game: Game,
+
+    /// Where the events for the player's connection go. It's replaced when the player
+    /// reconnects.
+    outbox: Sender<Event>,
+
+    /// How long the player took to clear the board, once they have.
+    time: Option<Duration>,
+
+    /// Whether the player left before the match started, giving up their seat.
+    left: bool,
+}
+
+/// A `Match` races players against each other on the same board. Each player has their
+/// own copy of the game, so they only see their own progress on the board and everyone's
+/// progress in the standings.
+pub struct Match {
+    /// The game every player starts with.
+    game: Game,
+
+    players: Vec<Player>,
+
+    /// The number of players the match waits for before it starts.
+    needed: usize,
+
+    /// When the match started.
+    started: Option<Instant>,
+
+    spectators: Spectators,
+}
+
+impl Match {
+    /// Create a match that starts once the given number of players have joined. Moving
+    /// bombs away from the first click would give players different boards, so the same
+    /// opening is revealed for everyone up front.
+    pub fn new(mut game: Game, needed: usize) -> Match {
+        if let Some((x, y)) = game.hint() {
+            let _ = game.perform_action(x, y, Action::Reveal);
+        }
+        game.history.clear();
+        Match {
+            game,
+            players: Vec::new(),
+            needed,
+            started: None,
+            spectators: Spectators::default(),
+        }
+    }
+
+    // Start the race and show everyone the board.
+    fn start(&mut self) {
+        self.started = Some(Instant::now());
+        for player in 0..self.players.len() {
+            self.connect(player, self.players[player].outbox.clone());
+        }
+        for player in 0..self.players.len() {
+            self.spectators.send(&Event::Watched {
+                player: player + 1,
+                event: Box::new(Event::Board(self.view(player))),
+            });
+        }
+        self.spectators.send(&Event::Standings(self.standings()));
+    }
+
+    // Returns true once every player is done with the board.
+    fn is_finished(&self) -> bool {
+        self.started.is_some() && self.players.iter().all(|player| player.game.game_over)
+    }
+
+    // Returns what can be shown of the player's board. Everyone races on the same board, so
+    // the board of a player that hit a mine stays hidden until the match is over.
+    fn view(&self, player: usize) -> PlayerView {
+        let game = &self.players[player].game;
+        match self.is_finished() {
+            true => game.player_view(),
+            false => PlayerView::concealed(game),
+        }
+    }
+
+    // Returns the number of players waiting for the match to start.
+    fn seated(&self) -> usize {
+        self.players.iter().filter(|player| !player.left).count()
+    }
+
+    // Returns how each player is doing, in the order they joined.
+    fn standings(&self) -> Vec<Standing> {
+        self.players
+            .iter()
+            .enumerate()
+            .map(|(index, player)| Standing {
+                player: index + 1,
+                progress: player.game.progress() * 100.0,
+                hit_mine: player.game.game_over && !player.game.game_won,
+                time: player.time.map(|time| time.as_millis() as u64),
+                three_bv_per_second: player
+                    .time
+                    .map(|time| player.game.three_bv() as f32 / time.as_secs_f32().max(0.001)),
+            })
+            .collect()
+    }
+
+    // Send an event about a player to them and the spectators.
+    fn show(&mut self, player: usize, event: Event) {
+        self.spectators.send(&Event::Watched {
+            player: player + 1,
+            event: Box::new(event.clone()),
+        });
+        self.send(player, event);
+    }
+
+    // Send an event to one player. Players that lost their connection miss it, but are
+    // caught up when they reconnect.
+    fn send(&self, player: usize, event: Event) {
+        let _ = self.players[player].outbox.send(event);
+    }
+
+    // Send an event to every player still in the match and the spectators.
+    fn broadcast(&mut self, event: Event) {
+        self.spectators.send(&event);
+        for player in 0..self.players.len() {
+            if !self.players[player].left {
+                self.send(player, event.clone());
+            }
+        }
+    }
+}
+
+impl Table for Match {
+    fn is_open(&self) -> bool {
+        self.seated() < self.needed
+    }
+
+    fn is_competitive(&self) -> bool {
+        true
+    }
+
+    // The match starts when the last player joins. New players take the seats of the
+    // players that left first.
+    fn add(&mut self, outbox: Sender<Event>) -> usize {
+        let seat = Player {
+            game: self.game.clone(),
+            outbox,
+            time: None,
+            left: false,
+        };
+        let player = match self.players.iter().position(|player| player.left) {
+            Some(player) => {
+                self.players[player] = seat;
+                player
+            }
+            None => {
+                self.players.push(seat);
+                self.players.len() - 1
+            }
+        };
+        match self.is_open() {
+            true => self.broadcast(Event::Waiting {
+                players: self.seated(),
+                needed: self.needed,
+            }),
+            false => self.start(),
+        }
+        player
+    }
+
+    fn connect(&mut self, player: usize, outbox: Sender<Event>) {
+        self.players[player].outbox = outbox;
+        let event = match self.started {
+            Some(_) => Event::MatchStarted {
+                player: player + 1,
+                players: self.players.len(),
+            },
+            None => Event::Waiting {
+                players: self.seated(),
+                needed: self.needed,
+            },
+        };
+        self.send(player, event);
+        self.send(player, Event::Board(self.view(player)));
+        if self.started.is_some() {
+            self.send(player, Event::Standings(self.standings()));
+        }
+    }
+
+    // Tell everyone how the player's action changed the standings.
+    fn perform(&mut self, player: usize, request: Request) {
+        if self.started.is_none() {
+            match request {
+                // Players that leave before the start give up their seat to the next player.
+                Request::Quit => {
+                    self.players[player].left = true;
+                    self.broadcast(Event::Waiting {
+                        players: self.seated(),
+                        needed: self.needed,
+                    });
+                }
+                _ => {
+                    let message = "The match hasn't started yet".to_string();
+                    self.send(player, Event::Invalid { message });
+                }
+            }
+            return;
+        }
+
+        match request {
+            Request::Action { x, y, action } => {
+                match self.players[player].game.perform_action(x, y, action) {
+                    Ok(response) => self.show(player, Event::Response(response)),
+                    Err(error) => {
+                        let message = error.to_string();
+                        self.send(player, Event::Error { error, message });
+                        return;
+                    }
+                }
+            }
+            Request::Cursor { x, y } => {
+                let player = player + 1;
+                self.spectators.send(&Event::Cursor { player, x, y });
+                return;
+            }
+            Request::NewGame => {
+                let message = "A new game can't be started during a match".to_string();
+                self.send(player, Event::Invalid { message });
+                return;
+            }
+            // Leaving counts as losing, so the match can still finish. Players that lose
+            // their connection leave this way once their session runs out.
+            Request::Quit => {
+                let game = &mut self.players[player].game;
+                if game.game_over {
+                    return;
+                }
+                game.finish_game(false);
+            }
+        }
+
+        let elapsed = self.started.map(|started| started.elapsed());
+        let current = &mut self.players[player];
+        if current.game.game_won && current.time.is_none() {
+            current.time = elapsed;
+        }
+        self.show(player, Event::Board(self.view(player)));
+        self.broadcast(Event::Standings(self.standings()));
+
+        // The whole board can be shown once everyone is done.
+        if self.is_finished() {
+            let mut results = self.standings();
+            results.sort_by(rank);
+            self.broadcast(Event::Results(results));
+            for player in 0..self.players.len() {
+                self.show(player, Event::Board(self.view(player)));
+            }
+        }
+    }
+
+    fn watch(&mut self, outbox: Sender<Event>) {
+        let _ = outbox.send(match self.started {
+            Some(_) => Event::Standings(self.standings()),
+            None => Event::Waiting {
+                players: self.seated(),
+                needed: self.needed,
+            },
+        });
+        for player in (0..self.players.len()).filter(|&player| !self.players[player].left) {
+            let _ = outbox.send(Event::Watched {
+                player: player + 1,
+                event: Box::new(Event::Board(self.view(player))),
+            });
+        }
+        self.spectators.add(outbox);
+    }
+}
+
+// Orders players that cleared the board by their time and then their 3BV/s, followed by
+// the rest by how far they got.
+fn rank(a: &Standing, b: &Standing) -> Ordering {
+    match (a.time, b.time) {
+        (Some(a_time), Some(b_time)) => a_time.cmp(&b_time).then_with(|| {
+            let a_speed = a.three_bv_per_second.unwrap_or_default();
+            let b_speed = b.three_bv_per_second.unwrap_or_default();
+            b_speed.total_cmp(&a_speed)
+        }),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => b.progress.total_cmp(&a.progress),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{self, Receiver};
+
+    use minesweeper_core::{Minefield, VisibleTile};
+
+    use super::*;
+
+    // The top two rows are revealed up front, which leaves three safe tiles to find.
+    const BOARD: &str = "...\n...\n*.*\n.*.";
+
+    // Returns a match on the board that starts once the given number of players join.
+    fn new_match(needed: usize) -> Match {
+        let minefield = Minefield::parse(BOARD).unwrap();
+        Match::new(Game::new(1, 1, 0).with_minefield(minefield), needed)
+    }
+
+    // Seat a player and return their seat and inbox.
+    fn join(table: &mut Match) -> (usize, Receiver<Event>) {
+        let (outbox, inbox) = mpsc::channel();
+        (table.add(outbox), inbox)
+    }
+
+    fn reveal(table: &mut Match, player: usize, x: usize, y: usize) {
+        let action = Action::Reveal;
+        table.perform(player, Request::Action { x, y, action });
+    }
+
+    // Returns the last board in the events.
+    fn last_board(events: &[Event]) -> PlayerView {
+        events
+            .iter()
+            .rev()
+            .find_map(|event| match event {
+                Event::Board(view) => Some(view.clone()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn standing(player: usize, progress: f32, time: Option<u64>, speed: f32) -> Standing {
+        Standing {
+            player,
+            progress,
+            hit_mine: time.is_none(),
+            time,
+            three_bv_per_second: time.map(|_| speed),
+        }
+    }
+
+    #[test]
+    fn players_who_leave_before_the_start_give_up_their_seat() {
+        let mut table = new_match(2);
+        let (first, _) = join(&mut table);
+        table.perform(first, Request::Quit);
+        assert!(table.is_open());
+
+        let (second, second_inbox) = join(&mut table);
+        assert_eq!(second, first);
+        let (third, _) = join(&mut table);
+        assert_eq!(third, 1);
+        assert!(!table.is_open());
+        let events: Vec<Event> = second_inbox.try_iter().collect();
+        assert!(events.iter().any(|event| matches!(
+            event,
+            Event::MatchStarted {
+                player: 1,
+                players: 2
+            }
+        )));
+    }
+
+    #[test]
+    fn actions_wait_for_the_start() {
+        let mut table = new_match(2);
+        let (player, inbox) = join(&mut table);
+        reveal(&mut table, player, 1, 2);
+        let events: Vec<Event> = inbox.try_iter().collect();
+        assert!(matches!(events.last(), Some(Event::Invalid { .. })));
+    }
+
+    #[test]
+    fn winners_rank_by_time_and_speed_before_the_rest() {
+        let mut standings = [
+            standing(1, 40.0, None, 0.0),
+            standing(2, 100.0, Some(9000), 1.0),
+            standing(3, 70.0, None, 0.0),
+            standing(4, 100.0, Some(5000), 1.0),
+            standing(5, 100.0, Some(5000), 2.0),
+        ];
+        standings.sort_by(rank);
+        let order: Vec<usize> = standings.iter().map(|standing| standing.player).collect();
+        assert_eq!(order, vec![5, 4, 2, 3, 1]);
+    }
+
+    #[test]
+    fn results_are_sent_once_everyone_is_done() {
+        let mut table = new_match(2);
+        let (winner, winner_inbox) = join(&mut table);
+        let (loser, loser_inbox) = join(&mut table);
+        for (x, y) in [(1, 2), (0, 3), (2, 3)] {
+            reveal(&mut table, winner, x, y);
+        }
+        assert!(
+            !winner_inbox
+                .try_iter()
+                .any(|event| matches!(event, Event::Results(_)))
+        );
+
+        table.perform(loser, Request::Quit);
+        let events: Vec<Event> = loser_inbox.try_iter().collect();
+        let results = events
+            .iter()
+            .find_map(|event| match event {
+                Event::Results(results) => Some(results.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].player, 1);
+        assert!(results[0].time.is_some() && results[0].progress == 100.0);
+        assert!(results[1].hit_mine && results[1].time.is_none());
+    }
+
+    #[test]
+    fn knocked_out_boards_stay_hidden_until_the_results() {
+        let mut table = new_match(2);
+        let (winner, _) = join(&mut table);
+        let (loser, loser_inbox) = join(&mut table);
+        reveal(&mut table, loser, 0, 2);
+        let view = last_board(&loser_inbox.try_iter().collect::<Vec<_>>());
+        assert!(view.game_over);
+        assert_eq!(view.tile(0, 2), VisibleTile::Mine(1));
+        assert_eq!(view.tile(2, 2), VisibleTile::Hidden);
+        assert_eq!(view.tile(1, 2), VisibleTile::Hidden);
+
+        for (x, y) in [(1, 2), (0, 3), (2, 3)] {
+            reveal(&mut table, winner, x, y);
+        }
+        let view = last_board(&loser_inbox.try_iter().collect::<Vec<_>>());
+        assert_eq!(view.tile(2, 2), VisibleTile::Mine(1));
+        assert_eq!(view.tile(1, 2), VisibleTile::Number(3));
+    }
+
+    #[test]
+    fn spectators_see_concealed_boards_until_the_results() {
+        let mut table = new_match(2);
+        let (winner, _) = join(&mut table);
+        let (loser, _) = join(&mut table);
+        let (outbox, spectator) = mpsc::channel();
+        table.watch(outbox);
+        reveal(&mut table, loser, 0, 2);
+
+        let boards = |events: Vec<Event>| -> Vec<PlayerView> {
+            events
+                .into_iter()
+                .filter_map(|event| match event {
+                    Event::Watched { player: 2, event } => match *event {
+                        Event::Board(view) => Some(view),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect()
+        };
+        let view = boards(spectator.try_iter().collect()).pop().unwrap();
+        assert_eq!(view.tile(0, 2), VisibleTile::Mine(1));
+        assert_eq!(view.tile(1, 3), VisibleTile::Hidden);
+
+        for (x, y) in [(1, 2), (0, 3), (2, 3)] {
+            reveal(&mut table, winner, x, y);
+        }
+        let view = boards(spectator.try_iter().collect()).pop().unwrap();
+        assert_eq!(view.tile(1, 3), VisibleTile::Mine(1));
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+};
+use minesweeper_core::{Action, Game, Mask, Minefield, Response};
+
+use crate::{Args, ui::BoardLayout};
+
+/// The `App` holds the game being played along with everything the terminal frontend
+/// needs to draw it, such as the cursor and the timer.
+pub struct App {
+    /// The flags the game was started with, used to create new games.
+    args: Args,
+
+    /// The mask loaded from the flags, if any.
+    mask: Option<Mask>,
+
+    /// The saved board loaded from the flags, if any.
+    minefield: Option<Minefield>,
+
+    pub game: Game,
+
+    /// The tile under the cursor.
+    pub cursor: (usize, usize),
+
+    /// When the first action of the game was done.
+    started: Option<Instant>,
+
+    /// How long the game took, once it's over.
+    finished: Option<Duration>,
+
+    /// A line of text shown below the board.
+    pub message: String,
+
+    /// Text to put on the clipboard the next time the screen is drawn.
+    pub copied: Option<String>,
+
+    /// Set when the player wants to leave.
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new(args: Args, mask: Option<Mask>, minefield: Option<Minefield>) -> App {
+        let game = args.new_game(mask.as_ref(), minefield.as_ref());
+        App {
+            cursor: first_cursor(&game),
+            args,
+            mask,
+            minefield,
+            game,
+            started: None,
+            finished: None,
+            message: String::new(),
+            copied: None,
+            quit: false,
+        }
+    }
+
+    /// Throw away the current game and start a new one on the same board.
+    pub fn new_game(&mut self) {
+        self.game = self
+            .args
+            .new_game(self.mask.as_ref(), self.minefield.as_ref());
+        self.cursor = first_cursor(&self.game);
+        self.started = None;
+        self.finished = None;
+        self.message = String::new();
+    }
+
+    /// Returns how long the current game has been played.
+    pub fn elapsed(&self) -> Duration {
+        match (self.started, self.finished) {
+            (_, Some(finished)) => finished,
+            (Some(started), None) => started.elapsed(),
+            (None, None) => Duration::ZERO,
+        }
+    }
+
+    /// Returns the number of mines that haven't been flagged. This goes negative when
+    /// there are more flags than mines.
+    pub fn mines_left(&self) -> i64 {
+        self.game.player_view().mines_left()
+    }
+
+    /// Update the game based on a key press or a mouse event.
+    pub fn handle_event(&mut self, event: Event) {
+        match event {
+            Event::Key(key) if key.kind == KeyEventKind::Press => self.handle_key(key),
+            Event::Mouse(mouse) => self.handle_mouse(mouse),
+            _ => {}
+        }
+    }
+
+    // Move the cursor and act on the tile under it with the keyboard.
+    fn handle_key(&mut self, key: KeyEvent) {
+        let (x, y) = self.cursor;
+        match key.code {
+            KeyCode::Left | KeyCode::Char('h') | KeyCode::Char('a') => self.move_cursor(-1, 0),
+            KeyCode::Right | KeyCode::Char('l') | KeyCode::Char('d') => self.move_cursor(1, 0),
+            KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('w') => self.move_cursor(0, -1),
+            KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('s') => self.move_cursor(0, 1),
+            KeyCode::Char(' ') | KeyCode::Enter => self.perform(x, y, Action::Reveal),
+            KeyCode::Char('f') => self.perform(x, y, Action::Flag),
+            KeyCode::Char('c') => self.perform(x, y, Action::Chord),
+            KeyCode::Char('?') => self.hint(),
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('n') => self.new_game(),
+            KeyCode::Char('y') => self.share(),
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            _ => {}
+        }
+    }
+
+    // Follow the mouse with the cursor and act on the tile that was clicked.
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let layout = BoardLayout::new(&self.game);
+        let (x, y) = match layout.tile_at(&self.game, mouse.column, mouse.row) {
+            Some(tile) => tile,
+            None => return,
+        };
+        self.cursor = (x, y);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => self.perform(x, y, Action::Reveal),
+            MouseEventKind::Down(MouseButton::Right) => self.perform(x, y, Action::Flag),
+            MouseEventKind::Down(MouseButton::Middle) => self.perform(x, y, Action::Chord),
+            _ => {}
+        }
+    }
+
+    // Move the cursor by the given number of tiles. Moving past the side of a layer moves
+    // into the layer next to it, just like the layers are drawn. Voids are skipped.
+    fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let (width, height) = (self.game.width as isize, self.game.height as isize);
+        let layers = self.game.depth as isize;
+        let (x, y) = self.cursor;
+        let mut column = (y as isize / height) * width + x as isize;
+        let mut row = y as isize % height;
+        loop {
+            column += dx;
+            row += dy;
+            if column < 0 || column >= width * layers || row < 0 || row >= height {
+                return;
+            }
+            let tile = (
+                (column % width) as usize,
+                ((column / width) * height + row) as usize,
+            );
+            if !self.game.is_void(tile.0, tile.1) {
+                self.cursor = tile;
+                return;
+            }
+        }
+    }
+
+    // Do the action on the given tile. Revealing a revealed tile chords it.
+    fn perform(&mut self, x: usize, y: usize, action: Action) {
+        if self.game.is_void(x, y) {
+            return;
+        }
+        let action = match action {
+            Action::Reveal if !self.game.player_view().tile(x, y).is_hidden() => Action::Chord,
+            action => action,
+        };
+        let response = match self.game.perform_action(x, y, action) {
+            Ok(response) => response,
+            Err(err) => {
+                self.message = err.to_string();
+                return;
+            }
+        };
+        self.message = String::new();
+        self.started.get_or_insert_with(Instant::now);
+
+        let message = match response {
+            Response::GameOver => "Boom! Press n for a new game or q to quit.",
+            Response::GameWon => "You won! Press n for a new game or q to quit.",
+            _ => return,
+        };
+        self.finished = Some(self.elapsed());
+        self.message = message.to_string();
+    }
+
+    // Move the cursor to a safe tile and reveal it.
+    fn hint(&mut self) {
+        match self.game.hint() {
+            Some((x, y)) => {
+                self.cursor = (x, y);
+                self.perform(x, y, Action::Reveal);
+            }
+            None => self.message = "There is nothing left to hint.".to_string(),
+        }
+    }
+
+    // Take back the last action.
+    fn undo(&mut self) {
+        if !self.game.undo() {
+            self.message = "There is nothing to undo.".to_string();
+        }
+    }
+
+    // Copy the board as text so it can be pasted into a chat.
+    fn share(&mut self) {
+        self.copied = Some(self.game.player_view().to_string());
+        self.message = "Copied the board to the clipboard.".to_string();
+    }
+}
+
+// Returns where the cursor starts, in the middle of the first layer on a tile that is part
+// of the board.
+fn first_cursor(game: &Game) -> (usize, usize) {
+    let middle = (game.width / 2, game.height / 2);
+    if !game.is_void(middle.0, middle.1) {
+        return middle;
+    }
+    (0..game.height)
+        .flat_map(|y| (0..game.width).map(move |x| (x, y)))
+        .find(|(x, y)| !game.is_void(*x, *y))
+        .unwrap_or_default()
+}
@@ -0,0 +1,201 @@
+mod app;
+mod ui;
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use app::App;
+use clap::{CommandFactory, Parser, error::ErrorKind};
+use crossterm::{
+    cursor,
+    event::{self, Event},
+    execute,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use minesweeper_core::{
+    FirstClick, Game, HexTopology, Mask, Minefield, SquareTopology, Topology, Variant,
+};
+
+/// How long to wait for input before checking whether the timer needs redrawing.
+const TICK: Duration = Duration::from_millis(250);
+
+#[derive(Parser, Debug)]
+#[clap(author = "The Marshians", version = "0.1.0", about = "Play Minesweeper in the terminal!", long_about = None)]
+pub struct Args {
+    #[clap(long, default_value_t = 16)]
+    /// The number of tiles in each row.
+    width: usize,
+
+    #[clap(long, default_value_t = 16)]
+    /// The number of rows in each layer.
+    height: usize,
+
+    #[clap(long, default_value_t = 1)]
+    /// The number of layers stacked on top of each other.
+    depth: usize,
+
+    #[clap(long, default_value_t = 40)]
+    /// The number of bombs on the board.
+    bombs: usize,
+
+    #[clap(long)]
+    /// Use hexagonal tiles with six neighbours.
+    hex: bool,
+
+    #[clap(short, long)]
+    /// Connect the edges of the board to the opposite edges.
+    wrap: bool,
+
+    #[clap(short, long)]
+    /// A PNG or ASCII art file that removes tiles from the board. Dark pixels and `.`
+    /// characters are removed. The mask replaces the width and height.
+    mask: Option<PathBuf>,
+
+    #[clap(long, conflicts_with_all = ["mask", "depth"])]
+    /// Play a saved board. `.mbf` files are read as MBF and everything else as ASCII art
+    /// where `*` is a mine and layers are separated by an empty line. The board replaces
+    /// the width, height, depth and bombs.
+    minefield: Option<PathBuf>,
+
+    #[clap(long)]
+    /// Let each bomb hold between one and three mines.
+    multi_mine: bool,
+
+    #[clap(short, long)]
+    /// Cycle through a question mark after the flag when marking tiles.
+    question_marks: bool,
+
+    #[clap(long, value_enum, default_value_t)]
+    /// How the bombs are kept away from the first revealed tile.
+    first_click: FirstClick,
+}
+
+impl Args {
+    /// Returns a new game with the board the flags describe.
+    pub fn new_game(&self, mask: Option<&Mask>, minefield: Option<&Minefield>) -> Game {
+        let topology: Arc<dyn Topology> = match self.hex {
+            true => Arc::new(HexTopology { wrap: self.wrap }),
+            false => Arc::new(SquareTopology { wrap: self.wrap }),
+        };
+        let mut game = Game::new(self.width, self.height, self.bombs)
+            .with_depth(self.depth)
+            .with_topology(topology)
+            .with_question_marks(self.question_marks)
+            .with_first_click(self.first_click);
+        if self.multi_mine {
+            game = game.with_variant(Variant::MultiMine);
+        }
+        if let Some(mask) = mask {
+            game = game.with_mask(mask.clone());
+        }
+        if let Some(minefield) = minefield {
+            game = game.with_minefield(minefield.clone());
+        }
+        game
+    }
+}
+
+// Puts the terminal back the way it was, even when the game panics.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<TerminalGuard> {
+        terminal::enable_raw_mode()?;
+        execute!(
+            io::stdout(),
+            EnterAlternateScreen,
+            event::EnableMouseCapture,
+            cursor::Hide
+        )?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(
+            io::stdout(),
+            cursor::Show,
+            event::DisableMouseCapture,
+            LeaveAlternateScreen
+        );
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+
+    // Load the mask if one was given.
+    let mask = args.mask.as_ref().map(|path| match Mask::load(path) {
+        Ok(mask) => mask,
+        Err(err) => {
+            let message = format!("{}: {}", path.display(), err);
+            Args::command()
+                .error(ErrorKind::InvalidValue, message)
+                .exit()
+        }
+    });
+
+    // Load the saved board if one was given.
+    let minefield = args
+        .minefield
+        .as_ref()
+        .map(|path| match Minefield::load(path) {
+            Ok(minefield) => minefield,
+            Err(err) => {
+                let message = format!("{}: {}", path.display(), err);
+                Args::command()
+                    .error(ErrorKind::InvalidValue, message)
+                    .exit()
+            }
+        });
+
+    // Make sure there is room for all the bombs.
+    let mut app = App::new(args, mask, minefield);
+    if app.game.bombs >= app.game.tile_count() {
+        let message = "there must be fewer bombs than tiles";
+        Args::command()
+            .error(ErrorKind::ValueValidation, message)
+            .exit();
+    }
+
+    // Make sure the edges of the board line up where they wrap.
+    if !app.game.topology.supports(app.game.width, app.game.height) {
+        let message = "wrapping hex boards need an even height";
+        Args::command()
+            .error(ErrorKind::ValueValidation, message)
+            .exit();
+    }
+
+    let _guard = TerminalGuard::enter()?;
+    let mut stdout = io::stdout();
+
+    // Only redraw when the screen would change, which is after input or when the timer
+    // ticks over to the next second.
+    let mut changed = true;
+    let mut shown = None;
+    while !app.quit {
+        let seconds = app.elapsed().as_secs();
+        if changed || shown != Some(seconds) {
+            ui::draw(&mut stdout, &app)?;
+            stdout.flush()?;
+            (changed, shown) = (false, Some(seconds));
+        }
+        if event::poll(TICK)? {
+            let event = event::read()?;
+            // Resizing the terminal can leave parts of the last frame behind.
+            if let Event::Resize(..) = event {
+                execute!(stdout, Clear(ClearType::All))?;
+            }
+            app.handle_event(event);
+            changed = true;
+        }
+        if let Some(text) = app.copied.take() {
+            ui::copy(&mut stdout, &text)?;
+        }
+    }
+    Ok(())
+}
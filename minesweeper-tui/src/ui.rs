@@ -0,0 +1,174 @@
+use std::io::{self, Write};
+
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{
+        Attribute, Color, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+    },
+    terminal::{Clear, ClearType},
+};
+use minesweeper_core::{Game, VisibleTile};
+
+use crate::app::App;
+
+/// The number of terminal columns each tile takes up.
+const TILE_WIDTH: u16 = 2;
+
+/// The number of columns left between the layers of the board.
+const LAYER_GAP: u16 = 2;
+
+/// The row the board starts on, below the mine counter and the timer.
+const BOARD_TOP: u16 = 2;
+
+/// The column the board starts on.
+const BOARD_LEFT: u16 = 2;
+
+/// The keys shown below the board.
+const HELP: &str =
+    "arrows/hjkl move  space reveal  f flag  c chord  ? hint  u undo  n new  y copy  q quit";
+
+/// The `BoardLayout` decides where each tile is drawn in the terminal. Layers are drawn
+/// side by side and hexagonal rows are shifted by half a tile, like the Bevy frontend.
+pub struct BoardLayout {
+    /// The number of columns from the start of one layer to the start of the next.
+    layer_stride: u16,
+}
+
+impl BoardLayout {
+    pub fn new(game: &Game) -> BoardLayout {
+        let (extent_x, _) = game.topology.extent(game.width, game.height);
+        BoardLayout {
+            layer_stride: (extent_x * TILE_WIDTH as f32).ceil() as u16 + LAYER_GAP,
+        }
+    }
+
+    /// Returns the column and row the given tile is drawn at.
+    pub fn position(&self, game: &Game, x: usize, y: usize) -> (u16, u16) {
+        let (layer, row) = (y / game.height, y % game.height);
+        let (layout_x, _) = game.topology.layout(x, row);
+        let column = BOARD_LEFT
+            + layer as u16 * self.layer_stride
+            + (layout_x * TILE_WIDTH as f32).round() as u16;
+        (column, BOARD_TOP + row as u16)
+    }
+
+    /// Returns the tile drawn at the given column and row, if any.
+    pub fn tile_at(&self, game: &Game, column: u16, row: u16) -> Option<(usize, usize)> {
+        let row = row.checked_sub(BOARD_TOP)? as usize;
+        if row >= game.height {
+            return None;
+        }
+        (0..game.depth)
+            .flat_map(|layer| (0..game.width).map(move |x| (x, layer * game.height + row)))
+            .find(|&(x, y)| {
+                let (start, _) = self.position(game, x, y);
+                !game.is_void(x, y) && (start..start + TILE_WIDTH).contains(&column)
+            })
+    }
+}
+
+/// Draw the mine counter, the timer, the board and the messages over the last frame. The
+/// screen isn't cleared first, so it doesn't flicker.
+pub fn draw(out: &mut impl Write, app: &App) -> io::Result<()> {
+    let game = &app.game;
+    queue!(
+        out,
+        MoveTo(BOARD_LEFT, 0),
+        Print(format!(
+            "Mines: {:<4} Time: {}",
+            app.mines_left(),
+            app.elapsed().as_secs()
+        )),
+        Clear(ClearType::UntilNewLine)
+    )?;
+
+    let layout = BoardLayout::new(game);
+    let view = game.player_view();
+    for y in 0..view.rows() {
+        for x in 0..view.width {
+            let (glyph, foreground, background) = match view.tile(x, y) {
+                VisibleTile::Void => continue,
+                tile => tile_style(tile),
+            };
+            let (column, row) = layout.position(game, x, y);
+            queue!(
+                out,
+                MoveTo(column, row),
+                SetForegroundColor(foreground),
+                SetBackgroundColor(background),
+            )?;
+            if app.cursor == (x, y) {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            queue!(
+                out,
+                Print(glyph),
+                SetAttribute(Attribute::Reset),
+                ResetColor
+            )?;
+        }
+    }
+
+    let bottom = BOARD_TOP + game.height as u16 + 1;
+    queue!(
+        out,
+        MoveTo(BOARD_LEFT, bottom),
+        Print(&app.message),
+        Clear(ClearType::UntilNewLine)
+    )?;
+    queue!(out, MoveTo(BOARD_LEFT, bottom + 1), Print(HELP))?;
+    Ok(())
+}
+
+/// Put the text on the clipboard with the OSC 52 escape sequence. The terminal copies it,
+/// so this works over SSH too, but some terminals ignore it.
+pub fn copy(out: &mut impl Write, text: &str) -> io::Result<()> {
+    write!(out, "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+    out.flush()
+}
+
+// Encode the bytes as base64, which OSC 52 expects.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+            group | (*byte as u32) << (16 - index * 8)
+        });
+        for index in 0..4 {
+            encoded.push(match index <= chunk.len() {
+                true => ALPHABET[(group >> (18 - index * 6)) as usize & 63] as char,
+                false => '=',
+            });
+        }
+    }
+    encoded
+}
+
+// Returns the character drawn for a tile and its colors.
+fn tile_style(tile: VisibleTile) -> (char, Color, Color) {
+    let (foreground, background) = match tile {
+        VisibleTile::Mine(_) => (Color::Red, Color::Reset),
+        VisibleTile::Number(0) => (Color::DarkGrey, Color::Reset),
+        VisibleTile::Number(number) => (number_color(number), Color::Reset),
+        VisibleTile::Hidden | VisibleTile::Void => (Color::Grey, Color::DarkGrey),
+        VisibleTile::Flag(_) => (Color::Red, Color::DarkGrey),
+        VisibleTile::Question => (Color::Yellow, Color::DarkGrey),
+    };
+    (tile.glyph(), foreground, background)
+}
+
+// Returns the classic color of a number.
+fn number_color(number: u8) -> Color {
+    match number {
+        1 => Color::Blue,
+        2 => Color::Green,
+        3 => Color::Red,
+        4 => Color::DarkBlue,
+        5 => Color::DarkRed,
+        6 => Color::Cyan,
+        7 => Color::Magenta,
+        _ => Color::Grey,
+    }
+}
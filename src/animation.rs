@@ -0,0 +1,286 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::f32::consts::PI;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use minesweeper_core::{PlayerView, Response, VisibleTile};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    game::{Game, TILE_SIZE_WITH_GAP},
+    minesweeper_plugin::TileLabel,
+    settings::Settings,
+    states::GameState,
+    theme::{Theme, TileImage},
+};
+
+/// The delay between each step of a cascading reveal.
+const REVEAL_STEP: Duration = Duration::from_millis(25);
+
+/// The delay between mines one tile further away from the mine that was hit.
+const EXPLOSION_STEP: Duration = Duration::from_millis(60);
+
+/// How long a tile takes to pop.
+const POP_DURATION: Duration = Duration::from_millis(150);
+
+/// The number of confetti particles thrown when the game is won.
+const CONFETTI_COUNT: usize = 200;
+
+/// How long confetti particles stay on the screen.
+const CONFETTI_LIFETIME: Duration = Duration::from_millis(2500);
+
+/// The downward acceleration of confetti particles.
+const GRAVITY: f32 = 600.0;
+
+/// The `AnimationSettings` control the animations. Speedrunners may want to turn them off
+/// so the board always shows the current state.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct AnimationSettings {
+    /// Whether tiles and the end of the game are animated at all.
+    pub enabled: bool,
+
+    /// How fast the animations play, where 2.0 is twice as fast.
+    pub speed: f32,
+}
+
+impl Default for AnimationSettings {
+    fn default() -> Self {
+        AnimationSettings {
+            enabled: true,
+            speed: 1.0,
+        }
+    }
+}
+
+impl AnimationSettings {
+    // Returns the duration adjusted for the speed of the animations.
+    fn scale(&self, duration: Duration) -> Duration {
+        duration.div_f32(self.speed.max(0.01))
+    }
+}
+
+/// A tile waiting to show a new image, such as one further out in a cascading reveal.
+#[derive(Component)]
+pub struct PendingImage {
+    image: TileImage,
+    timer: Timer,
+}
+
+impl PendingImage {
+    pub fn new(image: TileImage, delay: Duration) -> PendingImage {
+        PendingImage {
+            image,
+            timer: Timer::new(delay, TimerMode::Once),
+        }
+    }
+}
+
+/// A tile that grows and shrinks back to its normal size.
+#[derive(Component)]
+pub struct Pop {
+    timer: Timer,
+    scale: f32,
+}
+
+impl Pop {
+    /// Create a pop that grows the tile to the given scale.
+    pub fn new(settings: &AnimationSettings, scale: f32) -> Pop {
+        Pop {
+            timer: Timer::new(settings.scale(POP_DURATION), TimerMode::Once),
+            scale,
+        }
+    }
+}
+
+// A confetti particle thrown when the game is won.
+#[derive(Component)]
+struct Confetti {
+    velocity: Vec2,
+    spin: f32,
+    lifetime: Timer,
+}
+
+/// Returns how long each of the tiles changed by an action should wait before showing its
+/// new image. Revealed tiles cascade out from the clicked tile in flood fill order and
+/// mines explode out from the mine that was hit. Tiles that aren't included change right
+/// away.
+pub fn delays(
+    settings: &AnimationSettings,
+    game: &Game,
+    origin: (usize, usize),
+    result: &Response,
+) -> HashMap<(usize, usize), Duration> {
+    if !settings.enabled {
+        return HashMap::new();
+    }
+    match result {
+        Response::Reveal(revealed) => reveal_steps(game, origin, revealed)
+            .into_iter()
+            .map(|(tile, steps)| (tile, settings.scale(REVEAL_STEP * steps)))
+            .collect(),
+        Response::GameOver if !game.game_won => {
+            let view = game.player_view();
+            let (hit_x, hit_y) = hit_mine(game, &view, origin);
+            let origin_position = game.tile_position(hit_x, hit_y);
+            (0..view.rows())
+                .flat_map(|y| (0..view.width).map(move |x| (x, y)))
+                .filter(|&(x, y)| matches!(view.tile(x, y), VisibleTile::Mine(_)))
+                .map(|(x, y)| {
+                    let distance = game.tile_position(x, y).distance(origin_position);
+                    let steps = (distance / TILE_SIZE_WITH_GAP).round() as u32;
+                    ((x, y), settings.scale(EXPLOSION_STEP * steps))
+                })
+                .collect()
+        }
+        _ => HashMap::new(),
+    }
+}
+
+// Returns the mine that ended the game. That's the clicked tile unless the game was lost by
+// chording it, when it's the first mine the chord revealed around it.
+fn hit_mine(game: &Game, view: &PlayerView, origin: (usize, usize)) -> (usize, usize) {
+    let is_mine = |(x, y): (usize, usize)| matches!(view.tile(x, y), VisibleTile::Mine(_));
+    match is_mine(origin) {
+        true => origin,
+        false => game
+            .neighbours(origin.0, origin.1)
+            .into_iter()
+            .find(|&(x, y)| is_mine((x, y)) && !game.tile(x, y).flagged())
+            .unwrap_or(origin),
+    }
+}
+
+// Returns the number of flood fill steps each revealed tile is from the clicked tile.
+fn reveal_steps(
+    game: &Game,
+    origin: (usize, usize),
+    revealed: &HashSet<(usize, usize)>,
+) -> HashMap<(usize, usize), u32> {
+    let mut steps = HashMap::new();
+    let mut queue = VecDeque::from([(origin, 0)]);
+    if revealed.contains(&origin) {
+        steps.insert(origin, 0);
+    }
+    let mut visited = HashSet::from([origin]);
+    while let Some(((x, y), step)) = queue.pop_front() {
+        for neighbour in game.neighbours(x, y) {
+            if revealed.contains(&neighbour) && visited.insert(neighbour) {
+                steps.insert(neighbour, step + 1);
+                queue.push_back((neighbour, step + 1));
+            }
+        }
+    }
+    steps
+}
+
+/// The `AnimationPlugin` animates the tiles and the end of the game, as the animation
+/// settings ask.
+pub struct AnimationPlugin;
+
+impl Plugin for AnimationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (show_pending_images, animate_pops).chain())
+            .add_systems(Update, animate_confetti)
+            .add_systems(
+                OnEnter(GameState::GameOver),
+                throw_confetti.run_if(|game: Res<Game>, settings: Res<Settings>| {
+                    game.game_won && settings.animations.enabled
+                }),
+            );
+    }
+}
+
+// Show the new image of tiles once they are done waiting and pop them.
+fn show_pending_images(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut tile_query: Query<(Entity, &mut PendingImage, &mut Sprite, &Children)>,
+    mut label_query: Query<&mut Visibility, With<TileLabel>>,
+) {
+    for (entity, mut pending, mut sprite, children) in tile_query.iter_mut() {
+        if !pending.timer.tick(time.delta()).finished() {
+            continue;
+        }
+        if let Some(atlas) = &mut sprite.texture_atlas {
+            atlas.index = pending.image.index();
+        }
+        let scale = match pending.image {
+            TileImage::Bomb => 1.5,
+            _ => 1.1,
+        };
+        // The board may be removed this frame, when the game is restarted.
+        commands
+            .entity(entity)
+            .try_remove::<PendingImage>()
+            .try_insert(Pop::new(&settings.animations, scale));
+
+        // The label was hidden so the number didn't show before the tile was revealed.
+        for child in children {
+            if let Ok(mut visibility) = label_query.get_mut(*child) {
+                *visibility = Visibility::Inherited;
+            }
+        }
+    }
+}
+
+// Grow and shrink popping tiles.
+fn animate_pops(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pop_query: Query<(Entity, &mut Pop, &mut Transform)>,
+) {
+    for (entity, mut pop, mut transform) in pop_query.iter_mut() {
+        pop.timer.tick(time.delta());
+        let scale = 1.0 + (pop.scale - 1.0) * (PI * pop.timer.fraction()).sin();
+        transform.scale = Vec3::new(scale, scale, 1.0);
+        if pop.timer.finished() {
+            transform.scale = Vec3::ONE;
+            commands.entity(entity).try_remove::<Pop>();
+        }
+    }
+}
+
+// Throw confetti up from the middle of the screen.
+fn throw_confetti(mut commands: Commands, theme: Res<Theme>, settings: Res<Settings>) {
+    let settings = &settings.animations;
+    let mut rng = rand::rng();
+    let colors = theme.palette.numbers;
+    for i in 0..CONFETTI_COUNT {
+        let angle = rng.random_range(PI / 4.0..PI * 3.0 / 4.0);
+        let speed = rng.random_range(300.0..800.0) * settings.speed;
+        commands.spawn((
+            Name::new("Confetti"),
+            StateScoped(GameState::GameOver),
+            Confetti {
+                velocity: Vec2::from_angle(angle) * speed,
+                spin: rng.random_range(-10.0..10.0),
+                lifetime: Timer::new(settings.scale(CONFETTI_LIFETIME), TimerMode::Once),
+            },
+            Sprite::from_color(colors[i % colors.len()], Vec2::new(6.0, 10.0)),
+            Transform::from_xyz(0.0, 0.0, 10.0),
+        ));
+    }
+}
+
+// Move the confetti and remove it once it has been on screen long enough.
+fn animate_confetti(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut confetti_query: Query<(Entity, &mut Confetti, &mut Transform)>,
+) {
+    let settings = &settings.animations;
+    let delta = time.delta_secs();
+    for (entity, mut confetti, mut transform) in confetti_query.iter_mut() {
+        if confetti.lifetime.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        confetti.velocity.y -= GRAVITY * settings.speed * settings.speed * delta;
+        transform.translation += (confetti.velocity * delta).extend(0.0);
+        transform.rotate_z(confetti.spin * settings.speed * delta);
+    }
+}
@@ -8,29 +8,25 @@ pub struct EmbeddedAssetsPlugin;
 
 impl Plugin for EmbeddedAssetsPlugin {
     fn build(&self, app: &mut bevy::app::App) {
-        embedded_asset!(app, "", "assets/0.png");
-        embedded_asset!(app, "", "assets/1.png");
-        embedded_asset!(app, "", "assets/2.png");
-        embedded_asset!(app, "", "assets/3.png");
-        embedded_asset!(app, "", "assets/4.png");
-        embedded_asset!(app, "", "assets/5.png");
-        embedded_asset!(app, "", "assets/6.png");
-        embedded_asset!(app, "", "assets/7.png");
-        embedded_asset!(app, "", "assets/8.png");
-        embedded_asset!(app, "", "assets/flag.png");
-        embedded_asset!(app, "", "assets/closed.png");
-        embedded_asset!(app, "", "assets/bomb.png");
-    }
-}
+        // Each tile set holds every tile image, with the hexagonal versions in `hex`.
+        embedded_asset!(app, "", "assets/tiles.png");
+        embedded_asset!(app, "", "assets/hex/tiles.png");
+        embedded_asset!(app, "", "assets/latte/tiles.png");
+        embedded_asset!(app, "", "assets/latte/hex/tiles.png");
+        embedded_asset!(app, "", "assets/frappe/tiles.png");
+        embedded_asset!(app, "", "assets/frappe/hex/tiles.png");
+        embedded_asset!(app, "", "assets/macchiato/tiles.png");
+        embedded_asset!(app, "", "assets/macchiato/hex/tiles.png");
+        embedded_asset!(app, "", "assets/classic/tiles.png");
+        embedded_asset!(app, "", "assets/classic/hex/tiles.png");
 
-/// Returns the path to the asset with the given file name.
-/// The file name should not include the `.png` extension.
-pub fn asset_path(file_name: &str) -> String {
-    format!("{}/{}.png", ASSET_PREFIX, file_name)
-}
-
-/// Returns the path to the tile asset with the given number.
-/// The number should be between 0 and 8, inclusive.
-pub fn asset_path_tile(number: u8) -> String {
-    format!("{}/{}.png", ASSET_PREFIX, number)
+        // Sound effects.
+        embedded_asset!(app, "", "assets/sounds/reveal.wav");
+        embedded_asset!(app, "", "assets/sounds/flood_reveal.wav");
+        embedded_asset!(app, "", "assets/sounds/flag.wav");
+        embedded_asset!(app, "", "assets/sounds/unflag.wav");
+        embedded_asset!(app, "", "assets/sounds/chord.wav");
+        embedded_asset!(app, "", "assets/sounds/explosion.wav");
+        embedded_asset!(app, "", "assets/sounds/win.wav");
+    }
 }
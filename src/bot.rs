@@ -0,0 +1,49 @@
+use std::io::{self, BufRead, Write};
+
+use minesweeper_core::{Event, Game, Request};
+
+/// Let a program play the game over stdin and stdout. Each line it writes is a `Request`
+/// and each line written back is an `Event`. The board is sent when the game starts and
+/// after every action.
+pub fn run(mut game: Game) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    send(&mut stdout, &Event::Board(game.player_view()))?;
+
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => request,
+            Err(err) => {
+                let message = err.to_string();
+                send(&mut stdout, &Event::Invalid { message })?;
+                continue;
+            }
+        };
+
+        match request {
+            Request::Action { x, y, action } => match game.perform_action(x, y, action) {
+                Ok(response) => send(&mut stdout, &Event::Response(response))?,
+                Err(error) => {
+                    let message = error.to_string();
+                    send(&mut stdout, &Event::Error { error, message })?;
+                    continue;
+                }
+            },
+            Request::Cursor { .. } => continue,
+            Request::NewGame => game.reset(),
+            Request::Quit => break,
+        }
+        send(&mut stdout, &Event::Board(game.player_view()))?;
+    }
+    Ok(())
+}
+
+// Write the event as a line of JSON.
+fn send(out: &mut impl Write, event: &Event) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, event)?;
+    writeln!(out)?;
+    out.flush()
+}
@@ -1,5 +1,5 @@
 use bevy::color::Color;
-use catppuccin::PALETTE;
+use catppuccin::{FlavorColors, PALETTE};
 
 /// A helper function that converts a catppuccin color to a Bevy color.
 const fn color_convert(original: catppuccin::Color, alpha: f32) -> Color {
@@ -11,9 +11,104 @@ const fn color_convert(original: catppuccin::Color, alpha: f32) -> Color {
     )
 }
 
-/// Colors from the Catppuccin Mocha palette.
-pub const GREEN: Color = color_convert(PALETTE.mocha.colors.green, 1.0);
-pub const RED: Color = color_convert(PALETTE.mocha.colors.red, 1.0);
-pub const FLAMINGO: Color = color_convert(PALETTE.mocha.colors.flamingo, 1.0);
-pub const CRUST: Color = color_convert(PALETTE.mocha.colors.crust, 1.0);
-pub const BASE_80: Color = color_convert(PALETTE.mocha.colors.base, 0.8);
\ No newline at end of file
+/// The `Palette` struct holds all the colors used to draw the game.
+#[derive(Clone, Copy, Debug)]
+pub struct Palette {
+    /// The color behind the board.
+    pub background: Color,
+
+    /// The color of regular text, such as flag and mine counts.
+    pub text: Color,
+
+    /// The color of the bars drawn along the edges of a board that wraps around.
+    pub edge: Color,
+
+    /// The tint applied to highlighted tiles.
+    pub highlight: Color,
+
+    /// The background of popup windows.
+    pub panel: Color,
+
+    /// The border of popup windows and buttons.
+    pub border: Color,
+
+    /// The colors of a button when idle, hovered and pressed.
+    pub button: Color,
+    pub button_hovered: Color,
+    pub button_pressed: Color,
+
+    /// The color of the text on buttons.
+    pub button_text: Color,
+
+    /// The colors used to draw the numbers 1 to 8. Larger numbers use the last color.
+    pub numbers: [Color; 9],
+}
+
+impl Palette {
+    /// Returns the palette for the given Catppuccin flavor. The numbers follow the classic
+    /// scheme (blue, green, red, navy, maroon, teal, black, gray) using the closest colors
+    /// from the flavor.
+    pub const fn catppuccin(colors: &FlavorColors) -> Palette {
+        Palette {
+            background: color_convert(colors.base, 1.0),
+            text: color_convert(colors.text, 1.0),
+            edge: color_convert(colors.mauve, 1.0),
+            highlight: color_convert(colors.yellow, 1.0),
+            panel: color_convert(colors.base, 0.8),
+            border: color_convert(colors.crust, 1.0),
+            button: color_convert(colors.green, 1.0),
+            button_hovered: color_convert(colors.red, 1.0),
+            button_pressed: color_convert(colors.flamingo, 1.0),
+            button_text: color_convert(colors.crust, 1.0),
+            numbers: [
+                color_convert(colors.blue, 1.0),
+                color_convert(colors.green, 1.0),
+                color_convert(colors.red, 1.0),
+                color_convert(colors.lavender, 1.0),
+                color_convert(colors.maroon, 1.0),
+                color_convert(colors.teal, 1.0),
+                color_convert(colors.text, 1.0),
+                color_convert(colors.overlay1, 1.0),
+                color_convert(colors.peach, 1.0),
+            ],
+        }
+    }
+
+    /// Returns the palette of the classic Windows game.
+    pub const fn classic() -> Palette {
+        Palette {
+            background: Color::srgb_u8(192, 192, 192),
+            text: Color::BLACK,
+            edge: Color::srgb_u8(0, 0, 128),
+            highlight: Color::srgb_u8(255, 255, 160),
+            panel: Color::srgba_u8(192, 192, 192, 230),
+            border: Color::srgb_u8(128, 128, 128),
+            button: Color::srgb_u8(224, 224, 224),
+            button_hovered: Color::WHITE,
+            button_pressed: Color::srgb_u8(160, 160, 160),
+            button_text: Color::BLACK,
+            numbers: [
+                Color::srgb_u8(0, 0, 255),
+                Color::srgb_u8(0, 128, 0),
+                Color::srgb_u8(255, 0, 0),
+                Color::srgb_u8(0, 0, 128),
+                Color::srgb_u8(128, 0, 0),
+                Color::srgb_u8(0, 128, 128),
+                Color::BLACK,
+                Color::srgb_u8(128, 128, 128),
+                Color::srgb_u8(128, 0, 128),
+            ],
+        }
+    }
+
+    /// Returns the color used to draw the given number on a tile.
+    pub fn number(&self, number: u8) -> Color {
+        self.numbers[(number as usize).clamp(1, self.numbers.len()) - 1]
+    }
+}
+
+/// The palettes of the Catppuccin flavors.
+pub const LATTE: Palette = Palette::catppuccin(&PALETTE.latte.colors);
+pub const FRAPPE: Palette = Palette::catppuccin(&PALETTE.frappe.colors);
+pub const MACCHIATO: Palette = Palette::catppuccin(&PALETTE.macchiato.colors);
+pub const MOCHA: Palette = Palette::catppuccin(&PALETTE.mocha.colors);
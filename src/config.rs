@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use bevy::asset::ron;
+use serde::{Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("There is no configuration directory on this platform")]
+    NoDirectory,
+    #[error("Unable to write the configuration: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Unable to serialize the configuration: {0}")]
+    Ron(#[from] ron::Error),
+}
+
+/// Returns the directory where the game keeps its configuration files. This follows the
+/// XDG base directory spec, falling back to `%APPDATA%` on Windows and `~/.config`.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .or_else(|| std::env::var_os("APPDATA"))
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("minesweeper"))
+}
+
+/// Load the configuration file with the given name. The default is returned when the file
+/// doesn't exist or can't be read. This runs before logging is set up, so problems are
+/// written to stderr.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    let path = match config_dir() {
+        Some(directory) => directory.join(name),
+        None => return T::default(),
+    };
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return T::default(),
+        Err(err) => {
+            eprintln!("Ignoring {}: {}", path.display(), err);
+            return T::default();
+        }
+    };
+    match ron::from_str(&text) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Ignoring {}: {}", path.display(), err);
+            T::default()
+        }
+    }
+}
+
+/// Save the configuration file with the given name.
+pub fn save<T: Serialize>(name: &str, config: &T) -> Result<(), ConfigError> {
+    let directory = config_dir().ok_or(ConfigError::NoDirectory)?;
+    std::fs::create_dir_all(&directory)?;
+    let text = ron::ser::to_string_pretty(config, ron::ser::PrettyConfig::default())?;
+    std::fs::write(directory.join(name), text)?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use minesweeper_core::{Game, Minefield, MinefieldFormat};
+
+/// Generate a board, or load a saved one, and write it as ASCII art or MBF so it can be
+/// played in other Minesweeper programs.
+#[derive(clap::Args, Debug)]
+pub struct ExportArgs {
+    #[clap(long, default_value_t = 30)]
+    /// The number of tiles in each row of a generated board.
+    width: usize,
+
+    #[clap(long, default_value_t = 16)]
+    /// The number of rows of a generated board.
+    height: usize,
+
+    #[clap(long, default_value_t = 99)]
+    /// The number of bombs on a generated board.
+    bombs: usize,
+
+    #[clap(long)]
+    /// Place the bombs the same way every time a board is generated with this seed.
+    seed: Option<u64>,
+
+    #[clap(long)]
+    /// A saved board to convert instead of generating one. `.mbf` files are read as MBF
+    /// and everything else as ASCII art.
+    from: Option<PathBuf>,
+
+    #[clap(long, value_enum)]
+    /// The format to write. It defaults to the format of the output file's extension.
+    format: Option<MinefieldFormat>,
+
+    /// The file to write the board to. The board is written to stdout when left out.
+    output: Option<PathBuf>,
+}
+
+/// Write the board the arguments ask for.
+pub fn run(args: &ExportArgs) {
+    let minefield = match &args.from {
+        Some(path) => match Minefield::load(path) {
+            Ok(minefield) => minefield,
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                return;
+            }
+        },
+        None => {
+            // There must be fewer bombs than tiles.
+            let bombs = args.bombs.min((args.width * args.height).saturating_sub(1));
+            let mut game = Game::new(args.width, args.height, bombs);
+            if let Some(seed) = args.seed {
+                game = game.with_seed(seed);
+            }
+            Minefield::from_game(&game)
+        }
+    };
+
+    let format = match (args.format, &args.output) {
+        (Some(format), _) => format,
+        (None, Some(path)) => MinefieldFormat::of(path),
+        (None, None) => MinefieldFormat::Ascii,
+    };
+    let bytes = match minefield.to_bytes(format) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("Unable to write the board: {}", err);
+            return;
+        }
+    };
+    let written = match &args.output {
+        Some(path) => std::fs::write(path, bytes),
+        None => io::stdout().write_all(&bytes),
+    };
+    if let Err(err) = written {
+        eprintln!("Unable to write the board: {}", err);
+    }
+}
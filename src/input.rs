@@ -0,0 +1,297 @@
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+/// The things players can do with a key, mouse button or gamepad button.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputAction {
+    Reveal,
+    Flag,
+    Chord,
+    Hint,
+    Undo,
+    Reset,
+    Pause,
+    Theme,
+    Share,
+    Mute,
+    VolumeDown,
+    VolumeUp,
+    EffectsDown,
+    EffectsUp,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 14] = [
+        InputAction::Reveal,
+        InputAction::Flag,
+        InputAction::Chord,
+        InputAction::Hint,
+        InputAction::Undo,
+        InputAction::Reset,
+        InputAction::Pause,
+        InputAction::Theme,
+        InputAction::Share,
+        InputAction::Mute,
+        InputAction::VolumeDown,
+        InputAction::VolumeUp,
+        InputAction::EffectsDown,
+        InputAction::EffectsUp,
+    ];
+
+    /// Returns the name shown to players.
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputAction::Reveal => "Reveal",
+            InputAction::Flag => "Flag",
+            InputAction::Chord => "Chord",
+            InputAction::Hint => "Hint",
+            InputAction::Undo => "Undo",
+            InputAction::Reset => "New game",
+            InputAction::Pause => "Pause",
+            InputAction::Theme => "Next theme",
+            InputAction::Share => "Copy board",
+            InputAction::Mute => "Mute",
+            InputAction::VolumeDown => "Volume down",
+            InputAction::VolumeUp => "Volume up",
+            InputAction::EffectsDown => "Effects down",
+            InputAction::EffectsUp => "Effects up",
+        }
+    }
+}
+
+/// A key, mouse button or gamepad button that can be bound to an `InputAction`.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputButton {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButton),
+}
+
+impl InputButton {
+    /// Returns the name shown to players.
+    pub fn name(&self) -> String {
+        match self {
+            InputButton::Key(key) => format!("{:?}", key),
+            InputButton::Mouse(button) => format!("Mouse {:?}", button),
+            InputButton::Gamepad(button) => format!("Pad {:?}", button),
+        }
+    }
+}
+
+/// The buttons bound to a single action. An action can have one button of each kind, so
+/// it can be used from the keyboard, the mouse and a gamepad.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct Binding {
+    pub key: Option<KeyCode>,
+    pub mouse: Option<MouseButton>,
+    pub gamepad: Option<GamepadButton>,
+}
+
+impl Binding {
+    fn key(key: KeyCode) -> Binding {
+        Binding {
+            key: Some(key),
+            ..default()
+        }
+    }
+
+    fn mouse(mouse: MouseButton, gamepad: GamepadButton) -> Binding {
+        Binding {
+            mouse: Some(mouse),
+            gamepad: Some(gamepad),
+            ..default()
+        }
+    }
+
+    fn key_and_pad(key: KeyCode, gamepad: GamepadButton) -> Binding {
+        Binding {
+            key: Some(key),
+            gamepad: Some(gamepad),
+            ..default()
+        }
+    }
+
+    /// Returns the buttons that are bound.
+    pub fn buttons(&self) -> impl Iterator<Item = InputButton> + use<> {
+        let key = self.key.map(InputButton::Key);
+        let mouse = self.mouse.map(InputButton::Mouse);
+        let gamepad = self.gamepad.map(InputButton::Gamepad);
+        [key, mouse, gamepad].into_iter().flatten()
+    }
+
+    /// Returns true if the button is bound.
+    pub fn contains(&self, button: InputButton) -> bool {
+        self.buttons().any(|bound| bound == button)
+    }
+
+    /// Bind the button, replacing the button of the same kind.
+    pub fn set(&mut self, button: InputButton) {
+        match button {
+            InputButton::Key(key) => self.key = Some(key),
+            InputButton::Mouse(mouse) => self.mouse = Some(mouse),
+            InputButton::Gamepad(gamepad) => self.gamepad = Some(gamepad),
+        }
+    }
+
+    /// Returns the bound buttons as they are shown to players.
+    pub fn name(&self) -> String {
+        let names: Vec<String> = self.buttons().map(|button| button.name()).collect();
+        match names.is_empty() {
+            true => "None".to_string(),
+            false => names.join(" / "),
+        }
+    }
+}
+
+/// The `InputMap` holds the buttons bound to each `InputAction`. Tile actions are done on
+/// the tile under the cursor, whichever button they are bound to.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct InputMap {
+    pub reveal: Binding,
+    pub flag: Binding,
+    pub chord: Binding,
+    pub hint: Binding,
+    pub undo: Binding,
+    pub reset: Binding,
+    pub pause: Binding,
+    pub theme: Binding,
+    pub share: Binding,
+    pub mute: Binding,
+    pub volume_down: Binding,
+    pub volume_up: Binding,
+    pub effects_down: Binding,
+    pub effects_up: Binding,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        InputMap {
+            reveal: Binding::mouse(MouseButton::Left, GamepadButton::South),
+            flag: Binding::mouse(MouseButton::Right, GamepadButton::East),
+            chord: Binding::mouse(MouseButton::Middle, GamepadButton::West),
+            hint: Binding::key_and_pad(KeyCode::KeyH, GamepadButton::North),
+            undo: Binding::key_and_pad(KeyCode::KeyZ, GamepadButton::LeftTrigger),
+            reset: Binding::key_and_pad(KeyCode::KeyR, GamepadButton::Select),
+            pause: Binding::key_and_pad(KeyCode::Escape, GamepadButton::Start),
+            theme: Binding::key(KeyCode::KeyT),
+            share: Binding::key(KeyCode::KeyC),
+            mute: Binding::key(KeyCode::KeyM),
+            volume_down: Binding::key(KeyCode::Minus),
+            volume_up: Binding::key(KeyCode::Equal),
+            effects_down: Binding::key(KeyCode::BracketLeft),
+            effects_up: Binding::key(KeyCode::BracketRight),
+        }
+    }
+}
+
+impl InputMap {
+    /// Returns the buttons bound to the action.
+    pub fn binding(&self, action: InputAction) -> &Binding {
+        match action {
+            InputAction::Reveal => &self.reveal,
+            InputAction::Flag => &self.flag,
+            InputAction::Chord => &self.chord,
+            InputAction::Hint => &self.hint,
+            InputAction::Undo => &self.undo,
+            InputAction::Reset => &self.reset,
+            InputAction::Pause => &self.pause,
+            InputAction::Theme => &self.theme,
+            InputAction::Share => &self.share,
+            InputAction::Mute => &self.mute,
+            InputAction::VolumeDown => &self.volume_down,
+            InputAction::VolumeUp => &self.volume_up,
+            InputAction::EffectsDown => &self.effects_down,
+            InputAction::EffectsUp => &self.effects_up,
+        }
+    }
+
+    // Returns the buttons bound to the action.
+    fn binding_mut(&mut self, action: InputAction) -> &mut Binding {
+        match action {
+            InputAction::Reveal => &mut self.reveal,
+            InputAction::Flag => &mut self.flag,
+            InputAction::Chord => &mut self.chord,
+            InputAction::Hint => &mut self.hint,
+            InputAction::Undo => &mut self.undo,
+            InputAction::Reset => &mut self.reset,
+            InputAction::Pause => &mut self.pause,
+            InputAction::Theme => &mut self.theme,
+            InputAction::Share => &mut self.share,
+            InputAction::Mute => &mut self.mute,
+            InputAction::VolumeDown => &mut self.volume_down,
+            InputAction::VolumeUp => &mut self.volume_up,
+            InputAction::EffectsDown => &mut self.effects_down,
+            InputAction::EffectsUp => &mut self.effects_up,
+        }
+    }
+
+    /// Returns the action other than the given one that the button is bound to, if any.
+    pub fn conflict(&self, action: InputAction, button: InputButton) -> Option<InputAction> {
+        InputAction::ALL
+            .into_iter()
+            .find(|other| *other != action && self.binding(*other).contains(button))
+    }
+
+    /// Returns every pair of actions that share a button, along with the button. Only a
+    /// config file edited by hand can have these.
+    pub fn conflicts(&self) -> Vec<(InputButton, InputAction, InputAction)> {
+        let mut conflicts = Vec::new();
+        for (index, action) in InputAction::ALL.into_iter().enumerate() {
+            for other in InputAction::ALL.into_iter().skip(index + 1) {
+                for button in self.binding(action).buttons() {
+                    if self.binding(other).contains(button) {
+                        conflicts.push((button, action, other));
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Bind the button to the action, replacing the button of the same kind. The action
+    /// the button is already bound to is returned instead when there is one.
+    pub fn bind(&mut self, action: InputAction, button: InputButton) -> Result<(), InputAction> {
+        if let Some(other) = self.conflict(action, button) {
+            return Err(other);
+        }
+        self.binding_mut(action).set(button);
+        Ok(())
+    }
+}
+
+/// `ActionInput` reads the keyboard, the mouse and every gamepad and reports which
+/// `InputAction`s of an input map were pressed. The map is passed in rather than read from
+/// the settings, so systems can change the settings as well.
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    keyboard: Res<'w, ButtonInput<KeyCode>>,
+    mouse: Res<'w, ButtonInput<MouseButton>>,
+    gamepads: Query<'w, 's, &'static Gamepad>,
+}
+
+impl ActionInput<'_, '_> {
+    /// Returns true if a button bound to the action was pressed this frame.
+    pub fn just_pressed(&self, map: &InputMap, action: InputAction) -> bool {
+        let binding = map.binding(action);
+        binding
+            .key
+            .is_some_and(|key| self.keyboard.just_pressed(key))
+            || binding
+                .mouse
+                .is_some_and(|button| self.mouse.just_pressed(button))
+            || binding.gamepad.is_some_and(|button| {
+                self.gamepads
+                    .iter()
+                    .any(|gamepad| gamepad.just_pressed(button))
+            })
+    }
+
+    /// Returns the actions that were pressed this frame.
+    pub fn get_just_pressed(&self, map: &InputMap) -> impl Iterator<Item = InputAction> {
+        InputAction::ALL
+            .into_iter()
+            .filter(|action| self.just_pressed(map, *action))
+    }
+}
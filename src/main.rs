@@ -1,20 +1,70 @@
+mod animation;
 mod assets;
+mod bot;
 mod game;
 mod game_over_plugin;
+mod input;
+mod menu;
 mod states;
 mod minesweeper_plugin;
 mod popup;
+mod settings;
+mod share;
+mod simulate;
+mod sound;
+mod stats;
 mod colors;
+mod config;
+mod export;
+mod theme;
+mod theme_pack;
 
+use animation::AnimationPlugin;
 use assets::EmbeddedAssetsPlugin;
+use export::ExportArgs;
+use game::Game;
 use game_over_plugin::GameOverPlugin;
+use menu::MenuPlugin;
 use minesweeper_plugin::MinesweeperPlugin;
+use settings::{Difficulty, SETTINGS_CONFIG, Settings, SettingsPlugin};
+use share::SharePlugin;
+use simulate::SimulateArgs;
+use sound::SoundPlugin;
+use stats::StatsPlugin;
+use theme::{BuiltinTheme, ThemePlugin};
+use theme_pack::ThemePackPlugin;
 
 use bevy::log::{Level, LogPlugin};
 use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use clap::Parser;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, error::ErrorKind};
+use minesweeper_core::{
+    FirstClick, HexTopology, Mask, Minefield, SquareTopology, Topology, Variant,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// The board topologies that can be selected from the command line.
+#[derive(ValueEnum, Clone, Copy, Default, Debug)]
+enum Board {
+    /// Square tiles with eight neighbours.
+    #[default]
+    Square,
+
+    /// Hexagonal tiles with six neighbours.
+    Hex,
+}
+
+/// The tools that run instead of the game.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Play many games with a strategy and report the win rate, 3BV, guesses and timing.
+    Simulate(SimulateArgs),
+
+    /// Write a generated or saved board as ASCII art or MBF for other Minesweeper programs.
+    Export(ExportArgs),
+}
 
 #[derive(Parser, Debug)]
 #[clap(author = "The Marshians", version = "0.1.0", about = "Play Minesweeper!", long_about = None)]
@@ -30,11 +80,151 @@ struct Args {
     #[clap(short, long)]
     /// Show the world inspector.
     inspector: bool,
+
+    #[clap(short, long)]
+    /// Cycle through a question mark after the flag when marking tiles.
+    question_marks: bool,
+
+    #[clap(long, value_enum)]
+    /// The size of the board and the number of bombs.
+    difficulty: Option<Difficulty>,
+
+    #[clap(long, value_enum)]
+    /// How the bombs are kept away from the first revealed tile.
+    first_click: Option<FirstClick>,
+
+    #[clap(short, long, value_enum, default_value_t)]
+    /// The shape of the board.
+    board: Board,
+
+    #[clap(short, long)]
+    /// Connect the edges of the board to the opposite edges.
+    wrap: bool,
+
+    #[clap(long)]
+    /// The number of tiles in each row. This plays a custom board.
+    width: Option<usize>,
+
+    #[clap(long)]
+    /// The number of rows in each layer. This plays a custom board.
+    height: Option<usize>,
+
+    #[clap(long, default_value_t = 1)]
+    /// The number of layers stacked on top of each other.
+    depth: usize,
+
+    #[clap(long)]
+    /// The number of bombs on the board. This plays a custom board.
+    bombs: Option<usize>,
+
+    #[clap(short, long)]
+    /// A PNG or ASCII art file that removes tiles from the board. Dark pixels and `.`
+    /// characters are removed. The mask replaces the width and height.
+    mask: Option<PathBuf>,
+
+    #[clap(long, conflicts_with_all = ["mask", "depth"])]
+    /// Play a saved board. `.mbf` files are read as MBF and everything else as ASCII art
+    /// where `*` is a mine and layers are separated by an empty line. The board replaces
+    /// the width, height, depth and bombs.
+    minefield: Option<PathBuf>,
+
+    #[clap(long)]
+    /// Let each bomb hold between one and three mines. Numbers show the sum of the mines
+    /// around a tile and flags can be stacked.
+    multi_mine: bool,
+
+    #[clap(long)]
+    /// Place the bombs the same way every time the game is started with this seed.
+    seed: Option<u64>,
+
+    #[clap(long)]
+    /// Play without a window. The game is driven by JSON requests on stdin and writes the
+    /// board and the responses as JSON to stdout, one message per line.
+    bot: bool,
+
+    #[clap(long, value_enum)]
+    /// The colors and tiles used to draw the game. Press `T` to switch while playing.
+    theme: Option<BuiltinTheme>,
+
+    #[clap(long)]
+    /// A directory holding a `theme.ron` manifest and tile images to use instead of the
+    /// built in themes. The theme is reloaded when the files change.
+    theme_pack: Option<PathBuf>,
+
+    #[clap(long)]
+    /// Change tiles right away instead of animating them.
+    no_animations: bool,
+
+    #[clap(long)]
+    /// How fast animations play, where 2.0 is twice as fast.
+    animation_speed: Option<f32>,
+
+    #[clap(long)]
+    /// Play without sound.
+    mute: bool,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+impl Args {
+    /// Returns the settings with the flags that were given applied to them. The flags are
+    /// only used for this session, so they aren't saved.
+    fn apply(&self, mut settings: Settings) -> Settings {
+        if let Some(difficulty) = self.difficulty {
+            settings.difficulty = difficulty;
+        }
+        if self.width.is_some() || self.height.is_some() || self.bombs.is_some() {
+            settings.custom = settings.board_size();
+            settings.difficulty = Difficulty::Custom;
+        }
+        if let Some(width) = self.width {
+            settings.custom.width = width;
+        }
+        if let Some(height) = self.height {
+            settings.custom.height = height;
+        }
+        if let Some(bombs) = self.bombs {
+            settings.custom.bombs = bombs;
+        }
+        if let Some(theme) = self.theme {
+            settings.theme = theme;
+        }
+        if let Some(first_click) = self.first_click {
+            settings.first_click = first_click;
+        }
+        if self.question_marks {
+            settings.question_marks = true;
+        }
+        if self.no_animations {
+            settings.animations.enabled = false;
+        }
+        if let Some(speed) = self.animation_speed {
+            settings.animations.speed = speed;
+        }
+        if self.mute {
+            settings.audio.muted = true;
+        }
+        settings
+    }
 }
 
 fn main() {
     let args = Args::parse();
 
+    // Run the tool that was asked for instead of the game.
+    match &args.command {
+        Some(Command::Simulate(simulate)) => {
+            simulate::run(simulate);
+            return;
+        }
+        Some(Command::Export(export)) => {
+            export::run(export);
+            return;
+        }
+        None => {}
+    }
+
     // Setup console logging for debugging.
     let log_level = match (args.debug, args.trace) {
         (_, true) => Level::TRACE,
@@ -42,8 +232,92 @@ fn main() {
         _ => Level::INFO,
     };
 
+    // Load the saved settings and override them with the flags that were given.
+    let saved = config::load::<Settings>(SETTINGS_CONFIG);
+    let settings = args.apply(saved.clone());
+
+    // Determine how the tiles are connected.
+    let topology: Arc<dyn Topology> = match args.board {
+        Board::Square => Arc::new(SquareTopology { wrap: args.wrap }),
+        Board::Hex => Arc::new(HexTopology { wrap: args.wrap }),
+    };
+
+    // Load the mask if one was given.
+    let mask = args.mask.as_ref().map(|path| match Mask::load(path) {
+        Ok(mask) => mask,
+        Err(err) => {
+            let message = format!("{}: {}", path.display(), err);
+            Args::command()
+                .error(ErrorKind::InvalidValue, message)
+                .exit()
+        }
+    });
+
+    // Load the saved board if one was given.
+    let minefield = args
+        .minefield
+        .as_ref()
+        .map(|path| match Minefield::load(path) {
+            Ok(minefield) => minefield,
+            Err(err) => {
+                let message = format!("{}: {}", path.display(), err);
+                Args::command()
+                    .error(ErrorKind::InvalidValue, message)
+                    .exit()
+            }
+        });
+
+    let size = settings.board_size();
+    let mut game = minesweeper_core::Game::new(size.width, size.height, size.bombs)
+        .with_depth(args.depth)
+        .with_topology(topology)
+        .with_question_marks(settings.question_marks)
+        .with_first_click(settings.first_click);
+    if args.multi_mine {
+        game = game.with_variant(Variant::MultiMine);
+    }
+    if let Some(mask) = mask {
+        game = game.with_mask(mask);
+    }
+    if let Some(minefield) = minefield {
+        game = game.with_minefield(minefield);
+    }
+    if let Some(seed) = args.seed {
+        game = game.with_seed(seed);
+    }
+
+    // Make sure there is room for all the bombs.
+    if game.bombs >= game.tile_count() {
+        let message = "there must be fewer bombs than tiles";
+        Args::command()
+            .error(ErrorKind::ValueValidation, message)
+            .exit();
+    }
+
+    // Make sure the edges of the board line up where they wrap.
+    if !game.topology.supports(game.width, game.height) {
+        let message = "wrapping hex boards need an even height";
+        Args::command()
+            .error(ErrorKind::ValueValidation, message)
+            .exit();
+    }
+
+    // Let a program play instead of opening a window.
+    if args.bot {
+        if let Err(err) = bot::run(game) {
+            eprintln!("Unable to talk to the bot: {}", err);
+        }
+        return;
+    }
+
     // Initialize our app.
     let mut app = App::new();
+
+    // Theme packs add an asset source, so they must be added before the default plugins.
+    if let Some(directory) = args.theme_pack {
+        app.add_plugins(ThemePackPlugin { directory });
+    }
+
     app.add_plugins(DefaultPlugins.set(LogPlugin {
         level: log_level,
         ..default()
@@ -51,7 +325,20 @@ fn main() {
     .add_systems(Startup, |mut commands: Commands| {
         commands.spawn((Name::new("Camera2d"), Camera2d));
     })
-    .add_plugins((EmbeddedAssetsPlugin, MinesweeperPlugin, GameOverPlugin));
+    .add_plugins((
+        EmbeddedAssetsPlugin,
+        ThemePlugin {
+            theme: settings.theme,
+        },
+        MinesweeperPlugin { game: Game(game) },
+        GameOverPlugin,
+        MenuPlugin,
+        StatsPlugin,
+        SoundPlugin,
+        AnimationPlugin,
+        SharePlugin,
+        SettingsPlugin { settings, saved },
+    ));
 
     // Optionally add the inspector.
     if args.inspector {
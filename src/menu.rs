@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::Game,
+    input::{ActionInput, InputAction},
+    minesweeper_plugin::Board,
+    popup::{PopupButton, popup_window},
+    settings::Settings,
+    states::GameState,
+    stats::{Stats, board_name},
+    theme::Theme,
+};
+
+/// What a menu button does when it's pressed.
+#[derive(Component, Clone, Copy, Debug)]
+pub enum MenuAction {
+    /// Start a new game from the main menu.
+    Play,
+
+    /// Continue a paused game.
+    Resume,
+
+    /// Throw away the current game and start a new one.
+    Restart,
+
+    /// Show the settings screen.
+    Settings,
+
+    /// Show the statistics screen.
+    Stats,
+
+    /// Go back to the main menu.
+    MainMenu,
+
+    /// Go back to the screen that opened the statistics.
+    Back,
+
+    /// Close the game.
+    Quit,
+}
+
+/// The `ReturnState` resource remembers which screen opened the settings or statistics, so
+/// going back returns there.
+#[derive(Resource, Default)]
+pub struct ReturnState(pub GameState);
+
+/// The `MenuPlugin` adds the main menu, the pause menu and the settings and statistics
+/// screens. Each menu is scoped to its state, so it's removed when the state is left.
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReturnState>()
+            .add_systems(OnEnter(GameState::MainMenu), main_menu)
+            .add_systems(OnEnter(GameState::Paused), pause_menu)
+            .add_systems(OnEnter(GameState::Stats), stats_screen)
+            .add_systems(Update, (button_colors, menu_buttons, escape));
+    }
+}
+
+// Show the menu the game starts with.
+fn main_menu(mut commands: Commands, theme: Res<Theme>) {
+    commands.spawn((
+        StateScoped(GameState::MainMenu),
+        popup_window(
+            &theme,
+            "Minesweeper",
+            &[
+                ("Play", MenuAction::Play),
+                ("Settings", MenuAction::Settings),
+                ("Stats", MenuAction::Stats),
+                ("Quit", MenuAction::Quit),
+            ],
+        ),
+    ));
+}
+
+// Show the menu of a paused game.
+fn pause_menu(mut commands: Commands, theme: Res<Theme>) {
+    commands.spawn((
+        StateScoped(GameState::Paused),
+        popup_window(
+            &theme,
+            "Paused",
+            &[
+                ("Resume", MenuAction::Resume),
+                ("Restart", MenuAction::Restart),
+                ("Settings", MenuAction::Settings),
+                ("Main menu", MenuAction::MainMenu),
+            ],
+        ),
+    ));
+}
+
+// Show the statistics of past games.
+fn stats_screen(mut commands: Commands, theme: Res<Theme>, stats: Res<Stats>, game: Res<Game>) {
+    let board = board_name(&game);
+    let best = match stats.best_times.get(&board) {
+        Some(seconds) => format!("{:.1} seconds", seconds),
+        None => "none yet".to_string(),
+    };
+    let message = format!(
+        "Stats\n\nPlayed: {}\nWon: {} ({:.0}%)\nBest time on {}: {}",
+        stats.played,
+        stats.won,
+        stats.win_rate(),
+        board,
+        best,
+    );
+    commands.spawn((
+        StateScoped(GameState::Stats),
+        popup_window(&theme, &message, &[("Back", MenuAction::Back)]),
+    ));
+}
+
+// Color the buttons of popups and menus as they are hovered and pressed.
+fn button_colors(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<PopupButton>),
+    >,
+    theme: Res<Theme>,
+) {
+    for (interaction, mut color) in &mut interaction_query {
+        *color = match *interaction {
+            Interaction::Pressed => theme.palette.button_pressed.into(),
+            Interaction::Hovered => theme.palette.button_hovered.into(),
+            Interaction::None => theme.palette.button.into(),
+        };
+    }
+}
+
+// Do what the menu buttons say when they are pressed.
+fn menu_buttons(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &MenuAction), Changed<Interaction>>,
+    board_query: Query<Entity, With<Board>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut return_state: ResMut<ReturnState>,
+    mut game: ResMut<Game>,
+    mut exit: EventWriter<AppExit>,
+    settings: Res<Settings>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match action {
+            MenuAction::Play => {
+                *game = new_game(&game, &settings);
+                next_state.set(GameState::Playing);
+            }
+            MenuAction::Resume => next_state.set(GameState::Playing),
+            MenuAction::Restart => {
+                // The board is created again once there isn't one.
+                for board in board_query.iter() {
+                    commands.entity(board).despawn();
+                }
+                *game = new_game(&game, &settings);
+                next_state.set(GameState::Playing);
+            }
+            MenuAction::Settings | MenuAction::Stats => {
+                return_state.0 = *state.get();
+                next_state.set(match action {
+                    MenuAction::Settings => GameState::Settings,
+                    _ => GameState::Stats,
+                });
+            }
+            MenuAction::MainMenu => next_state.set(GameState::MainMenu),
+            MenuAction::Back => next_state.set(return_state.0),
+            MenuAction::Quit => {
+                exit.write(AppExit::Success);
+            }
+        }
+    }
+}
+
+/// Returns a new game played with the current settings. Changes to the board in the
+/// settings are used from the next game on.
+pub fn new_game(game: &Game, settings: &Settings) -> Game {
+    let size = settings.board_size();
+    let mut game = game
+        .0
+        .clone()
+        .with_first_click(settings.first_click)
+        .with_question_marks(settings.question_marks)
+        .with_size(size.width, size.height, size.bombs);
+    // Make sure there is room for all the bombs, even on a masked board.
+    if game.bombs >= game.tile_count() {
+        let (width, height) = (game.width, game.height);
+        let bombs = game.tile_count().saturating_sub(1);
+        game = game.with_size(width, height, bombs);
+    }
+    // Wrapping hex boards need an even height, so add a row when the settings ask for an
+    // odd one.
+    if !game.topology.supports(game.width, game.height) {
+        let (width, height, bombs) = (game.width, game.height + 1, game.bombs);
+        game = game.with_size(width, height, bombs);
+    }
+    Game(game)
+}
+
+// Pause and resume the game with the pause control, and leave the settings and statistics
+// screens.
+fn escape(
+    input: ActionInput,
+    settings: Res<Settings>,
+    state: Res<State<GameState>>,
+    return_state: Res<ReturnState>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !input.just_pressed(&settings.input, InputAction::Pause) {
+        return;
+    }
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        GameState::Settings | GameState::Stats => next_state.set(return_state.0),
+        GameState::MainMenu | GameState::GameOver => {}
+    }
+}
@@ -1,85 +1,366 @@
+use std::collections::HashSet;
+
 use crate::{
-    assets::{asset_path, asset_path_tile},
-    game::{Game, Response},
+    animation::{self, PendingImage, Pop},
+    game::{Game, TILE_SIZE_WITH_GAP},
+    input::{ActionInput, InputAction},
+    menu,
+    settings::Settings,
+    sound::Sound,
     states::GameState,
+    theme::{Theme, TileImage},
 };
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::{prelude::*, time::Stopwatch, window::PrimaryWindow};
+use minesweeper_core::{Action, Response, VisibleTile};
 
 #[derive(Component)]
-pub struct MinesweeperPlugin;
+pub struct MinesweeperPlugin {
+    /// The game to play.
+    pub game: Game,
+}
 
 impl Plugin for MinesweeperPlugin {
     fn build(&self, app: &mut App) {
         // Add out game resource.
-        app.insert_resource(Game::new(30, 16, 20))
+        app.insert_resource(self.game.clone())
             // Initialize the game state.
             .init_state::<GameState>()
-            // Add the game creation and update.
-            .add_systems(OnEnter(GameState::Playing), create_game)
-            .add_systems(Update, update_game.run_if(in_state(GameState::Playing)));
+            .enable_state_scoped_entities::<GameState>()
+            // Add the layout of the tile set and the time spent on the game.
+            .init_resource::<TileAtlas>()
+            .init_resource::<GameTimer>()
+            // Add the game creation and update. The board is kept while the game is paused
+            // and only created again for a new game, once the old board is gone.
+            .add_systems(
+                Update,
+                create_game.run_if(
+                    in_state(GameState::Playing)
+                        .and(|board: Query<(), With<Board>>| board.is_empty()),
+                ),
+            )
+            .add_systems(OnEnter(GameState::MainMenu), despawn_board)
+            .add_systems(
+                Update,
+                (update_game, highlight_neighbours, tick_timer)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(
+                Update,
+                reset_game.run_if(in_state(GameState::Playing).or(in_state(GameState::GameOver))),
+            )
+            .add_systems(Update, show_board.run_if(state_changed::<GameState>))
+            .add_systems(Update, reskin_board.run_if(resource_changed::<Theme>))
+            .add_systems(
+                Update,
+                update_tile_labels
+                    .after(update_game)
+                    .run_if(resource_changed::<Game>.or(resource_changed::<Theme>)),
+            );
+    }
+}
+
+/// The root of all the entities that make up the board.
+#[derive(Component)]
+pub struct Board;
+
+/// A marker for the sprites of the tiles.
+#[derive(Component)]
+pub struct Tile;
+
+/// The `GameTimer` resource tracks how long the current game has been played. It only
+/// runs while playing, so pausing doesn't count.
+#[derive(Resource, Default)]
+pub struct GameTimer(pub Stopwatch);
+
+/// The text drawn on top of a tile for its number, or its flag and mine counts.
+#[derive(Component)]
+pub struct TileLabel;
+
+/// A marker for the bars drawn along the edges of a board that wraps around.
+#[derive(Component)]
+pub struct WrapEdge;
+
+/// The `TileAtlas` resource holds the layout shared by all the tile sprites. Tiles change
+/// their look by changing their index into the layout.
+#[derive(Resource)]
+pub struct TileAtlas {
+    pub layout: Handle<TextureAtlasLayout>,
+}
+
+impl FromWorld for TileAtlas {
+    // Create the layout of the tile set for the shape of the board.
+    fn from_world(world: &mut World) -> Self {
+        let shape = world.resource::<Game>().topology.shape();
+        let mut layouts = world.resource_mut::<Assets<TextureAtlasLayout>>();
+        TileAtlas {
+            layout: layouts.add(TileImage::layout(shape)),
+        }
+    }
+}
+
+/// Returns the image that shows what the player can see of a tile.
+fn tile_image(tile: VisibleTile) -> TileImage {
+    match tile {
+        VisibleTile::Mine(_) => TileImage::Bomb,
+        VisibleTile::Number(_) => TileImage::Open,
+        VisibleTile::Hidden | VisibleTile::Void => TileImage::Closed,
+        VisibleTile::Flag(_) => TileImage::Flag,
+        VisibleTile::Question => TileImage::Question,
     }
 }
 
 // Initialize the game by spawning a grid of tiles.
-fn create_game(mut commands: Commands, game: Res<Game>, asset_server: Res<AssetServer>) {
+fn create_game(
+    mut commands: Commands,
+    game: Res<Game>,
+    theme: Res<Theme>,
+    atlas: Res<TileAtlas>,
+    asset_server: Res<AssetServer>,
+    mut timer: ResMut<GameTimer>,
+) {
+    timer.0.reset();
+    let board = commands
+        .spawn((Name::new("Board"), Board, Transform::default(), Visibility::default()))
+        .id();
+
     // For each tile in the game, spawn a sprite entity with the closed image.
-    for row in 0..game.height {
+    let tileset = asset_server.load(theme.tileset_path(game.topology.shape()));
+    for row in 0..game.rows() {
         for column in 0..game.width {
+            // Voids are not part of the board, so they don't get a sprite.
+            if game.is_void(column, row) {
+                continue;
+            }
             let tile_position = game.tile_position(column, row);
             commands.spawn((
                 Name::new(format!("Cell ({}, {})", row, column)),
-                Sprite::from_image(asset_server.load(asset_path("closed"))),
+                Tile,
+                ChildOf(board),
+                Sprite::from_atlas_image(
+                    tileset.clone(),
+                    TextureAtlas {
+                        layout: atlas.layout.clone(),
+                        index: TileImage::Closed.index(),
+                    },
+                ),
                 Transform {
                     translation: Vec3::new(tile_position.x, tile_position.y, 1.0),
                     ..default()
                 },
+                children![(
+                    TileLabel,
+                    Text2d::default(),
+                    theme.text_font(20.0),
+                    TextColor(theme.palette.text),
+                    Transform::from_xyz(0.0, 0.0, 1.0),
+                )],
             ));
         }
     }
+
+    if game.topology.wraps() {
+        spawn_wrap_edges(&mut commands, board, &game, &theme);
+    }
 }
 
-// Update the game state based on mouse input.
-fn update_game(
+// Remove the board when going back to the main menu.
+fn despawn_board(mut commands: Commands, board_query: Query<Entity, With<Board>>) {
+    for board in board_query.iter() {
+        commands.entity(board).despawn();
+    }
+}
+
+// Hide the board unless it's being played or the game just ended, so a paused game can't
+// be studied while the timer is stopped.
+fn show_board(
+    state: Res<State<GameState>>,
+    mut board_query: Query<&mut Visibility, With<Board>>,
+) {
+    for mut visibility in board_query.iter_mut() {
+        *visibility = match state.get() {
+            GameState::Playing | GameState::GameOver => Visibility::Inherited,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+// Count the time spent playing.
+fn tick_timer(time: Res<Time>, mut timer: ResMut<GameTimer>) {
+    timer.0.tick(time.delta());
+}
+
+// Draw a bar along each edge of every layer so it's clear the edges are connected.
+fn spawn_wrap_edges(commands: &mut Commands, board: Entity, game: &Game, theme: &Theme) {
+    const THICKNESS: f32 = 4.0;
+    let (extent_x, extent_y) = game.topology.extent(game.width, game.height);
+    let width = extent_x * TILE_SIZE_WITH_GAP;
+    let height = extent_y * TILE_SIZE_WITH_GAP;
+    let vertical = Vec2::new(THICKNESS, height);
+    let horizontal = Vec2::new(width, THICKNESS);
+
+    for layer in 0..game.depth {
+        // The top left tile of the layer is centered on its position, so the layer starts
+        // half a tile further out.
+        let top_left = game.tile_position(0, layer * game.height);
+        let left = top_left.x - TILE_SIZE_WITH_GAP / 2.0;
+        let top = top_left.y + TILE_SIZE_WITH_GAP / 2.0;
+        let center = Vec2::new(left + width / 2.0, top - height / 2.0);
+
+        let edges = [
+            ("Left", Vec2::new(left, center.y), vertical),
+            ("Right", Vec2::new(left + width, center.y), vertical),
+            ("Top", Vec2::new(center.x, top), horizontal),
+            ("Bottom", Vec2::new(center.x, top - height), horizontal),
+        ];
+        for (name, position, size) in edges {
+            commands.spawn((
+                Name::new(format!("Wrap Edge ({}, {})", layer, name)),
+                WrapEdge,
+                ChildOf(board),
+                Sprite::from_color(theme.palette.edge, size),
+                Transform::from_translation(position.extend(0.5)),
+            ));
+        }
+    }
+}
+
+// Tint the neighbours of the hovered tile on layered boards, so it's easier to follow
+// them across the layers.
+fn highlight_neighbours(
     window_query: Query<&Window, With<PrimaryWindow>>,
-    mouse_button_input: Res<ButtonInput<MouseButton>>,
-    asset_server: Res<AssetServer>,
-    mut game: ResMut<Game>,
-    mut tile_query: Query<(&mut Sprite, &Transform), With<Sprite>>,
-    mut game_state: ResMut<NextState<GameState>>,
+    game: Res<Game>,
+    theme: Res<Theme>,
+    mut hovered: Local<Option<(usize, usize)>>,
+    mut tile_query: Query<(&mut Sprite, &Transform), With<Tile>>,
 ) {
-    // We aren't interested in anything other than left or right mouse button presses.
-    if !mouse_button_input.just_pressed(MouseButton::Right)
-        && !mouse_button_input.just_pressed(MouseButton::Left)
-    {
+    if game.depth == 1 {
         return;
     }
 
-    // Get the mouse position in window coordinates.
-    let cursor_position = match window_query.single() {
-        Ok(window) => match window.cursor_position() {
-            Some(position) => position,
-            None => return,
-        },
+    // Find the tile under the cursor, if any.
+    let window = match window_query.single() {
+        Ok(window) => window,
         Err(_) => return,
     };
+    let tile = window
+        .cursor_position()
+        .and_then(|position| game.world_to_tile(game.window_to_world(window, position)));
 
-    // Convert window coordinates to world coordinates.
-    let world_position = game.window_to_world(window_query.single().unwrap(), cursor_position);
+    // We only need to update the sprites when something changed.
+    if tile == *hovered && !game.is_changed() && !theme.is_changed() {
+        return;
+    }
+    *hovered = tile;
 
-    // Get the tile coordinates from the world coordinates.
-    let (tile_x, tile_y) = match game.world_to_tile(world_position) {
-        Some((x, y)) => (x, y),
-        None => return,
+    let neighbours: HashSet<(usize, usize)> = match tile {
+        Some((x, y)) => game.neighbours(x, y).into_iter().collect(),
+        None => HashSet::new(),
     };
-    debug!("Clicked on tile: ({}, {})", tile_x, tile_y);
+    for (mut sprite, transform) in tile_query.iter_mut() {
+        let tile_position = match game.world_to_tile(transform.translation.truncate()) {
+            Some(position) => position,
+            None => continue,
+        };
+        sprite.color = match neighbours.contains(&tile_position) {
+            true => theme.palette.highlight,
+            false => Color::WHITE,
+        };
+    }
+}
 
-    // Determine the action based on the mouse button pressed
-    let action = match mouse_button_input.just_pressed(MouseButton::Left) {
-        true => crate::game::Action::Reveal,
-        false => crate::game::Action::Flag,
+// Start a new game with the reset control.
+fn reset_game(
+    mut commands: Commands,
+    input: ActionInput,
+    settings: Res<Settings>,
+    board_query: Query<Entity, With<Board>>,
+    mut game: ResMut<Game>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if !input.just_pressed(&settings.input, InputAction::Reset) {
+        return;
+    }
+    // The board is created again once there isn't one.
+    for board in board_query.iter() {
+        commands.entity(board).despawn();
+    }
+    *game = menu::new_game(&game, &settings);
+    game_state.set(GameState::Playing);
+}
+
+// Returns the tile under the cursor, if any.
+fn hovered_tile(
+    window_query: &Query<&Window, With<PrimaryWindow>>,
+    game: &Game,
+) -> Option<(usize, usize)> {
+    let window = window_query.single().ok()?;
+    let cursor_position = window.cursor_position()?;
+    game.world_to_tile(game.window_to_world(window, cursor_position))
+}
+
+// Update the game state based on the controls in the settings. Tile actions are done on
+// the tile under the cursor, while hints pick their own tile.
+fn update_game(
+    mut commands: Commands,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    input: ActionInput,
+    settings: Res<Settings>,
+    mut sounds: EventWriter<Sound>,
+    mut game: ResMut<Game>,
+    mut tile_query: Query<(Entity, &mut Sprite, &Transform), With<Tile>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let map = &settings.input;
+
+    // Undoing puts back the whole board, so every tile is redrawn.
+    if input.just_pressed(map, InputAction::Undo) {
+        if game.undo() {
+            debug!("Undid the last action");
+            let view = game.player_view();
+            for (entity, mut sprite, transform) in tile_query.iter_mut() {
+                let (x, y) = match game.world_to_tile(transform.translation.truncate()) {
+                    Some(position) => position,
+                    None => continue,
+                };
+                if let Some(atlas) = &mut sprite.texture_atlas {
+                    atlas.index = tile_image(view.tile(x, y)).index();
+                }
+                commands.entity(entity).remove::<PendingImage>();
+            }
+        }
+        return;
+    }
+
+    // Determine the tile and the action based on the control that was pressed. Revealing
+    // a revealed tile chords it, just like the chord control does.
+    let (tile_x, tile_y, action) = if input.just_pressed(map, InputAction::Hint) {
+        match game.hint() {
+            Some((x, y)) => (x, y, Action::Reveal),
+            None => return,
+        }
+    } else {
+        let action = if input.just_pressed(map, InputAction::Chord) {
+            Action::Chord
+        } else if input.just_pressed(map, InputAction::Reveal) {
+            Action::Reveal
+        } else if input.just_pressed(map, InputAction::Flag) {
+            Action::Flag
+        } else {
+            return;
+        };
+        let (x, y) = match hovered_tile(&window_query, &game) {
+            Some(tile) => tile,
+            None => return,
+        };
+        match action == Action::Reveal && !game.player_view().tile(x, y).is_hidden() {
+            true => (x, y, Action::Chord),
+            false => (x, y, action),
+        }
     };
+    debug!("Action on tile: ({}, {})", tile_x, tile_y);
 
     // Perform the action on the game resource.
+    let chording = action == Action::Chord;
     let result = match game.perform_action(tile_x, tile_y, action) {
         Ok(result) => result,
         Err(err) => {
@@ -89,55 +370,167 @@ fn update_game(
     };
     debug!("Action result: {:?}", result);
 
+    // Play a sound for what happened.
+    let sound = match result {
+        Response::GameOver => Some(Sound::Explosion),
+        Response::GameWon => Some(Sound::Win),
+        Response::Flag | Response::Question => Some(Sound::Flag),
+        Response::Unflag => Some(Sound::Unflag),
+        Response::Reveal(ref revealed_tiles) if revealed_tiles.is_empty() => None,
+        Response::Reveal(_) if chording => Some(Sound::Chord),
+        Response::Reveal(ref revealed_tiles) if revealed_tiles.len() > 1 => {
+            Some(Sound::FloodReveal)
+        }
+        Response::Reveal(_) => Some(Sound::Reveal),
+    };
+    if let Some(sound) = sound {
+        sounds.write(sound);
+    }
+
+    // Tiles further from the click wait before they change so they can be animated.
+    let settings = &settings.animations;
+    let delays = animation::delays(settings, &game, (tile_x, tile_y), &result);
+    let marked = matches!(
+        result,
+        Response::Flag | Response::Question | Response::Unflag
+    );
+
     // We want to loop through all the tile and see which ones need to change.
-    for (mut sprite, transform) in tile_query.iter_mut() {
+    let view = game.player_view();
+    for (entity, mut sprite, transform) in tile_query.iter_mut() {
         // Get the tile position from the transform.
         let tile_position = match game.world_to_tile(transform.translation.truncate()) {
             Some(position) => position,
             None => continue, // Skip if the tile position is not valid
         };
 
-        // Based on the result, we want may want to update the tile sprite.
-        match result {
-            // If the game is over, we reveal all the tiles and show the game over screen.
-            Response::GameOver | Response::GameWon => {
-                debug!("Game Over!");
-                let tile = game.tile(tile_position.0, tile_position.1);
-                if tile.bomb {
-                    sprite.image = asset_server.load(asset_path("bomb"));
-                } else {
-                    sprite.image = asset_server.load(asset_path_tile(
-                        game.tile_number(tile_position.0, tile_position.1),
-                    ));
-                }
-                game_state.set(GameState::GameOver);
-            }
+        // Based on the result, we may want to update the tile sprite.
+        let changed = match result {
+            // If the game is over, we reveal all the tiles.
+            Response::GameOver | Response::GameWon => true,
 
             // In this case, we reveal all the tiles in the revealed list.
-            Response::Reveal(ref revealed_tiles) => {
-                if revealed_tiles.contains(&tile_position) {
-                    trace!("Revealed tile: ({}, {})", tile_position.0, tile_position.1);
-                    sprite.image = asset_server.load(asset_path_tile(
-                        game.tile_number(tile_position.0, tile_position.1),
-                    ));
-                }
-            }
+            Response::Reveal(ref revealed_tiles) => revealed_tiles.contains(&tile_position),
 
-            // In this case, we flag the tile.
-            Response::Flag => {
-                if tile_x == tile_position.0 && tile_y == tile_position.1 {
-                    sprite.image = asset_server.load(asset_path("flag"));
-                    break;
-                }
+            // In these cases, only the marked tile changes.
+            Response::Flag | Response::Question | Response::Unflag => {
+                tile_position == (tile_x, tile_y)
             }
-
-            // In this case, we unflag the tile.
-            Response::Unflag => {
-                if tile_x == tile_position.0 && tile_y == tile_position.1 {
-                    sprite.image = asset_server.load(asset_path("closed"));
-                    break;
+        };
+        if !changed {
+            continue;
+        }
+        trace!("Updated tile: ({}, {})", tile_position.0, tile_position.1);
+        let image = tile_image(view.tile(tile_position.0, tile_position.1));
+        match delays.get(&tile_position) {
+            Some(delay) => {
+                commands
+                    .entity(entity)
+                    .insert(PendingImage::new(image, *delay));
+            }
+            None => {
+                if let Some(atlas) = &mut sprite.texture_atlas {
+                    atlas.index = image.index();
                 }
+                commands.entity(entity).remove::<PendingImage>();
             }
         }
+
+        // Marking a tile makes it pop.
+        if settings.enabled && marked {
+            commands.entity(entity).insert(Pop::new(settings, 1.3));
+        }
+    }
+
+    // Show the game over screen once the game is won or lost.
+    if matches!(result, Response::GameOver | Response::GameWon) {
+        debug!("Game Over!");
+        game_state.set(GameState::GameOver);
+    }
+}
+
+// Swap the tile set of every tile and the color of the edges when the theme changes.
+// Tiles keep their index, so they keep showing the same state.
+fn reskin_board(
+    game: Res<Game>,
+    theme: Res<Theme>,
+    asset_server: Res<AssetServer>,
+    mut tile_query: Query<&mut Sprite, (With<Tile>, Without<WrapEdge>)>,
+    mut edge_query: Query<&mut Sprite, With<WrapEdge>>,
+) {
+    let tileset = asset_server.load(theme.tileset_path(game.topology.shape()));
+    for mut sprite in tile_query.iter_mut() {
+        sprite.image = tileset.clone();
+    }
+    for mut sprite in edge_query.iter_mut() {
+        sprite.color = theme.palette.edge;
+    }
+}
+
+// Update the text drawn on the tiles. Numbers are drawn in the middle of the tile, while
+// flag and mine counts are drawn smaller in the corner.
+fn update_tile_labels(
+    game: Res<Game>,
+    theme: Res<Theme>,
+    tile_query: Query<(&Transform, Has<PendingImage>), Without<TileLabel>>,
+    mut label_query: Query<
+        (
+            &ChildOf,
+            &mut Text2d,
+            &mut TextFont,
+            &mut TextColor,
+            &mut Transform,
+            &mut Visibility,
+        ),
+        With<TileLabel>,
+    >,
+) {
+    let view = game.player_view();
+    for (child_of, mut text, mut font, mut color, mut transform, mut visibility) in
+        label_query.iter_mut()
+    {
+        let (parent, pending) = match tile_query.get(child_of.parent()) {
+            Ok(parent) => parent,
+            Err(_) => continue,
+        };
+        let tile_position = match game.world_to_tile(parent.translation.truncate()) {
+            Some(position) => position,
+            None => continue,
+        };
+
+        // Tiles waiting to be revealed show their label once they are revealed. Only what
+        // changed is written, so the labels aren't laid out again on every action.
+        visibility.set_if_neq(match pending {
+            true => Visibility::Hidden,
+            false => Visibility::Inherited,
+        });
+
+        let tile = view.tile(tile_position.0, tile_position.1);
+        let number = match tile {
+            VisibleTile::Number(number) => number,
+            _ => 0,
+        };
+        let (label, corner) = match tile {
+            VisibleTile::Mine(mines) if mines > 1 => (mines.to_string(), true),
+            VisibleTile::Flag(count) if count > 1 => (count.to_string(), true),
+            VisibleTile::Number(number) if number > 0 => (number.to_string(), false),
+            _ => (String::new(), false),
+        };
+
+        if text.0 != label {
+            text.0 = label;
+        }
+        let font_size = if corner { 14.0 } else { 20.0 };
+        if font.font_size != font_size || font.font != theme.font {
+            *font = theme.text_font(font_size);
+        }
+        let (translation, text_color) = match corner {
+            true => (Vec3::new(8.0, -8.0, 1.0), theme.palette.text),
+            false => (Vec3::new(0.0, 0.0, 1.0), theme.palette.number(number)),
+        };
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+        color.set_if_neq(TextColor(text_color));
     }
 }
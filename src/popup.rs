@@ -1,67 +1,96 @@
-use bevy::prelude::*;
+use bevy::{ecs::spawn::SpawnIter, prelude::*};
 
-use crate::colors::{BASE_80, CRUST, GREEN};
+use crate::theme::Theme;
 
-/// Create a popup windows with a message and a button.
-pub fn popup_window(msg: &str, button_text: &str) -> impl Bundle + use<> {
+/// The size of the buttons in a popup.
+const BUTTON_SIZE: Vec2 = Vec2::new(200.0, 55.0);
+
+/// A marker for the window of a popup.
+#[derive(Component)]
+pub struct PopupPanel;
+
+/// A marker for the buttons of a popup.
+#[derive(Component)]
+pub struct PopupButton;
+
+/// A marker for the text in a popup, so it can be restyled when the theme changes.
+#[derive(Component)]
+pub enum PopupText {
+    Message,
+    Button,
+}
+
+/// Create a popup windows with a message and a column of buttons. Each button gets the
+/// component it's paired with, so presses can be told apart.
+pub fn popup_window<B: Component + Clone>(
+    theme: &Theme,
+    msg: &str,
+    buttons: &[(&str, B)],
+) -> impl Bundle + use<B> {
+    let buttons: Vec<_> = buttons
+        .iter()
+        .map(|(text, marker)| button(theme, text, marker.clone(), BUTTON_SIZE))
+        .collect();
     (
         (
             Name::new("Popup"),
+            PopupPanel,
             Node {
-                top: Val::Percent(32.5),
+                top: Val::Percent(20.0),
                 left: Val::Percent(30.0),
                 width: Val::Percent(40.0),
-                height: Val::Percent(35.0),
                 align_items: AlignItems::Center,
                 justify_content: JustifyContent::FlexStart,
                 flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(15.0),
                 border: UiRect::all(Val::Px(5.0)),
                 margin: UiRect::all(Val::Px(10.0)),
                 padding: UiRect::all(Val::Px(30.0)),
                 ..default()
             },
-            BorderColor(CRUST),
-            BackgroundColor(BASE_80),
+            BorderColor(theme.palette.border),
+            BackgroundColor(theme.palette.panel),
         ),
-        children![Text::new(msg), button(button_text)],
+        Children::spawn((
+            Spawn((
+                Text::new(msg),
+                PopupText::Message,
+                theme.text_font(20.0),
+                TextColor(theme.palette.text),
+                TextLayout::new_with_justify(JustifyText::Center),
+            )),
+            SpawnIter(buttons.into_iter()),
+        )),
     )
 }
 
-// Add a button to the popup window.
-fn button(text: &str) -> impl Bundle + use<> {
+/// Create a button of the given size that looks like the buttons of a popup.
+pub fn button<B: Component>(
+    theme: &Theme,
+    text: &str,
+    marker: B,
+    size: Vec2,
+) -> impl Bundle + use<B> {
     (
+        Button,
+        PopupButton,
+        marker,
         Node {
-            width: Val::Percent(100.0),
-            height: Val::Percent(100.0),
-            align_items: AlignItems::Center,
+            width: Val::Px(size.x),
+            height: Val::Px(size.y),
+            border: UiRect::all(Val::Px(5.0)),
             justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
             ..default()
         },
-        Transform {
-            translation: Vec3::new(0.0, 0.0, -5.0),
-            ..default()
-        },
+        BorderColor(theme.palette.border),
+        BorderRadius::MAX,
+        BackgroundColor(theme.palette.button),
         children![(
-            Button,
-            Node {
-                width: Val::Px(150.0),
-                height: Val::Px(65.0),
-                border: UiRect::all(Val::Px(5.0)),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
-                ..default()
-            },
-            BorderColor(CRUST),
-            BorderRadius::MAX,
-            BackgroundColor(GREEN),
-            children![(
-                Text::new(text),
-                TextFont {
-                    font_size: 20.0,
-                    ..default()
-                },
-                TextColor(CRUST),
-            )]
+            Text::new(text),
+            PopupText::Button,
+            theme.text_font(20.0),
+            TextColor(theme.palette.button_text),
         )],
     )
 }
@@ -0,0 +1,534 @@
+use bevy::{ecs::system::SystemParam, input::InputSystem, prelude::*, ui::UiSystem};
+use clap::ValueEnum;
+use minesweeper_core::FirstClick;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation::AnimationSettings,
+    config,
+    input::{InputAction, InputButton, InputMap},
+    menu::ReturnState,
+    popup::{PopupPanel, PopupText, button},
+    sound::AudioSettings,
+    states::GameState,
+    theme::{BuiltinTheme, Theme},
+};
+
+/// The name of the file the settings are saved to.
+pub const SETTINGS_CONFIG: &str = "settings.ron";
+
+/// The speeds the animations can be switched between on the settings screen.
+const ANIMATION_SPEEDS: [f32; 4] = [0.5, 1.0, 2.0, 4.0];
+
+/// How much the volume changes with each step.
+const VOLUME_STEP: f32 = 0.1;
+
+/// The size of the buttons on the settings screen.
+const SETTING_BUTTON_SIZE: Vec2 = Vec2::new(220.0, 30.0);
+
+/// The size of a board and the number of bombs on it.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BoardSize {
+    pub width: usize,
+    pub height: usize,
+    pub bombs: usize,
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        BoardSize {
+            width: 30,
+            height: 16,
+            bombs: 20,
+        }
+    }
+}
+
+/// The sizes of board that can be picked.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Difficulty {
+    /// A 9x9 board with 10 bombs.
+    Beginner,
+
+    /// A 16x16 board with 40 bombs.
+    Intermediate,
+
+    /// A 30x16 board with 99 bombs.
+    Expert,
+
+    /// The board size saved in the settings.
+    #[default]
+    Custom,
+}
+
+impl Difficulty {
+    /// Returns the size of the board, using the given size for custom boards.
+    pub fn size(&self, custom: BoardSize) -> BoardSize {
+        let (width, height, bombs) = match self {
+            Difficulty::Beginner => (9, 9, 10),
+            Difficulty::Intermediate => (16, 16, 40),
+            Difficulty::Expert => (30, 16, 99),
+            Difficulty::Custom => return custom,
+        };
+        BoardSize {
+            width,
+            height,
+            bombs,
+        }
+    }
+}
+
+/// The `Settings` resource holds everything players can change on the settings screen.
+/// Changes to the board are used for the next game.
+#[derive(Resource, Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    /// The size of the board.
+    pub difficulty: Difficulty,
+
+    /// The size of the board when the difficulty is `Custom`.
+    pub custom: BoardSize,
+
+    /// The built in theme used to draw the game.
+    pub theme: BuiltinTheme,
+
+    /// How the bombs are kept away from the first revealed tile.
+    pub first_click: FirstClick,
+
+    /// Whether marking tiles cycles through a question mark after the flag.
+    pub question_marks: bool,
+
+    /// How tiles and the end of the game are animated.
+    pub animations: AnimationSettings,
+
+    /// How loud the game is.
+    pub audio: AudioSettings,
+
+    /// The keys, mouse buttons and gamepad buttons bound to each action.
+    pub input: InputMap,
+}
+
+impl Settings {
+    /// Returns the size of the board the next game is played on.
+    pub fn board_size(&self) -> BoardSize {
+        self.difficulty.size(self.custom)
+    }
+}
+
+/// The `SavedSettings` resource holds the settings as they are saved in the config file.
+/// They differ from the `Settings` resource when command line flags override them.
+#[derive(Resource, Clone, Debug)]
+pub struct SavedSettings(pub Settings);
+
+/// `SettingsMut` changes the settings used by this session and the saved settings at the
+/// same time, then saves them. Command line overrides that aren't changed stay out of the
+/// config file.
+#[derive(SystemParam)]
+pub struct SettingsMut<'w> {
+    settings: ResMut<'w, Settings>,
+    saved: ResMut<'w, SavedSettings>,
+}
+
+impl SettingsMut<'_> {
+    /// Returns the settings used by this session.
+    pub fn get(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Apply the change to the session and saved settings and save them.
+    pub fn change(&mut self, change: impl Fn(&mut Settings)) {
+        change(&mut self.settings);
+        change(&mut self.saved.0);
+        if let Err(err) = config::save(SETTINGS_CONFIG, &self.saved.0) {
+            error!("Unable to save the settings: {}", err);
+        }
+    }
+}
+
+/// What a button on the settings screen changes.
+#[derive(Component, Clone, Copy, Debug)]
+enum SettingsAction {
+    Difficulty,
+    Theme,
+    FirstClick,
+    QuestionMarks,
+    Animations,
+    AnimationSpeed,
+    Volume,
+    Effects,
+    Muted,
+    Input(InputAction),
+    ResetInput,
+    Back,
+}
+
+// A marker for the settings screen, so it can be redrawn when the settings change.
+#[derive(Component)]
+struct SettingsScreen;
+
+// The action waiting for a button press to be bound to it, if any, and why the last
+// button pressed couldn't be bound.
+#[derive(Resource, Default)]
+struct Rebinding {
+    action: Option<InputAction>,
+    conflict: Option<String>,
+}
+
+/// The `SettingsPlugin` adds the settings and the screen that changes them.
+pub struct SettingsPlugin {
+    /// The settings used by this session.
+    pub settings: Settings,
+
+    /// The settings loaded from the config file.
+    pub saved: Settings,
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        for (button, first, second) in self.settings.input.conflicts() {
+            warn!(
+                "{} is bound to both {} and {}",
+                button.name(),
+                first.name(),
+                second.name()
+            );
+        }
+
+        app.insert_resource(self.settings.clone())
+            .insert_resource(SavedSettings(self.saved.clone()))
+            .init_resource::<Rebinding>()
+            .add_systems(OnEnter(GameState::Settings), settings_screen)
+            .add_systems(
+                OnExit(GameState::Settings),
+                |mut rebinding: ResMut<Rebinding>| {
+                    *rebinding = Rebinding::default();
+                },
+            )
+            .add_systems(
+                Update,
+                (
+                    settings_buttons,
+                    redraw_settings_screen
+                        .run_if(resource_changed::<Settings>.or(resource_changed::<Rebinding>)),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Settings)),
+            )
+            // Buttons are bound before any other system sees them, including the buttons
+            // on the screen, so they don't also do what they were bound to before.
+            .add_systems(
+                PreUpdate,
+                rebind_button
+                    .after(InputSystem)
+                    .before(UiSystem::Focus)
+                    .run_if(|rebinding: Res<Rebinding>| rebinding.action.is_some()),
+            );
+    }
+}
+
+// Show the settings screen.
+fn settings_screen(
+    mut commands: Commands,
+    theme: Res<Theme>,
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+) {
+    let on_off = |enabled: bool| match enabled {
+        true => "On".to_string(),
+        false => "Off".to_string(),
+    };
+    let size = settings.board_size();
+    let rows = [
+        (
+            "Difficulty",
+            format!(
+                "{:?} ({}x{}, {})",
+                settings.difficulty, size.width, size.height, size.bombs
+            ),
+            SettingsAction::Difficulty,
+        ),
+        (
+            "Theme",
+            settings.theme.name().to_string(),
+            SettingsAction::Theme,
+        ),
+        (
+            "First click",
+            format!("{:?}", settings.first_click),
+            SettingsAction::FirstClick,
+        ),
+        (
+            "Question marks",
+            on_off(settings.question_marks),
+            SettingsAction::QuestionMarks,
+        ),
+        (
+            "Animations",
+            on_off(settings.animations.enabled),
+            SettingsAction::Animations,
+        ),
+        (
+            "Animation speed",
+            format!("{}x", settings.animations.speed),
+            SettingsAction::AnimationSpeed,
+        ),
+        (
+            "Volume",
+            format!("{:.0}%", settings.audio.master * 100.0),
+            SettingsAction::Volume,
+        ),
+        (
+            "Effects",
+            format!("{:.0}%", settings.audio.effects * 100.0),
+            SettingsAction::Effects,
+        ),
+        ("Muted", on_off(settings.audio.muted), SettingsAction::Muted),
+    ];
+    let controls = InputAction::ALL.map(|action| {
+        let buttons = match rebinding.action == Some(action) {
+            true => "Press a button...".to_string(),
+            false => settings.input.binding(action).name(),
+        };
+        (action.name(), buttons, SettingsAction::Input(action))
+    });
+    let note = match &rebinding.conflict {
+        Some(conflict) => conflict.clone(),
+        None => "Board changes are used for the next game. Press a control, then the new \
+                 key, mouse button or gamepad button."
+            .to_string(),
+    };
+
+    let label = |text: &str| {
+        (
+            Text::new(text),
+            PopupText::Message,
+            theme.text_font(16.0),
+            TextColor(theme.palette.text),
+        )
+    };
+    commands
+        .spawn((
+            Name::new("Settings"),
+            SettingsScreen,
+            StateScoped(GameState::Settings),
+            PopupPanel,
+            Node {
+                top: Val::Percent(5.0),
+                left: Val::Percent(15.0),
+                width: Val::Percent(70.0),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(15.0),
+                border: UiRect::all(Val::Px(5.0)),
+                padding: UiRect::all(Val::Px(20.0)),
+                ..default()
+            },
+            BorderColor(theme.palette.border),
+            BackgroundColor(theme.palette.panel),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Settings"),
+                PopupText::Message,
+                theme.text_font(20.0),
+                TextColor(theme.palette.text),
+            ));
+            parent
+                .spawn(Node {
+                    display: Display::Grid,
+                    grid_template_columns: RepeatedGridTrack::auto(2),
+                    column_gap: Val::Px(15.0),
+                    row_gap: Val::Px(5.0),
+                    align_items: AlignItems::Center,
+                    ..default()
+                })
+                .with_children(|grid| {
+                    for (name, value, action) in rows {
+                        grid.spawn(label(name));
+                        grid.spawn(button(&theme, &value, action, SETTING_BUTTON_SIZE));
+                    }
+                });
+            // There are many controls, so they are shown two to a row.
+            parent
+                .spawn(Node {
+                    display: Display::Grid,
+                    grid_template_columns: RepeatedGridTrack::auto(4),
+                    column_gap: Val::Px(15.0),
+                    row_gap: Val::Px(5.0),
+                    align_items: AlignItems::Center,
+                    ..default()
+                })
+                .with_children(|grid| {
+                    for (name, value, action) in controls {
+                        grid.spawn(label(name));
+                        grid.spawn(button(&theme, &value, action, SETTING_BUTTON_SIZE));
+                    }
+                });
+            parent.spawn(label(&note));
+            parent
+                .spawn(Node {
+                    column_gap: Val::Px(15.0),
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn(button(
+                        &theme,
+                        "Reset controls",
+                        SettingsAction::ResetInput,
+                        SETTING_BUTTON_SIZE,
+                    ));
+                    row.spawn(button(
+                        &theme,
+                        "Back",
+                        SettingsAction::Back,
+                        SETTING_BUTTON_SIZE,
+                    ));
+                });
+        });
+}
+
+// Redraw the settings screen so it shows the current settings.
+fn redraw_settings_screen(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<SettingsScreen>>,
+    theme: Res<Theme>,
+    settings: Res<Settings>,
+    rebinding: Res<Rebinding>,
+) {
+    for screen in screen_query.iter() {
+        commands.entity(screen).despawn();
+    }
+    settings_screen(commands, theme, settings, rebinding);
+}
+
+// Change the setting of the button that was pressed.
+fn settings_buttons(
+    interaction_query: Query<(&Interaction, &SettingsAction), Changed<Interaction>>,
+    mut settings: SettingsMut,
+    mut rebinding: ResMut<Rebinding>,
+    return_state: Res<ReturnState>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, action) in &interaction_query {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let current = settings.get().clone();
+        match action {
+            SettingsAction::Difficulty => {
+                let next = next_in(Difficulty::value_variants(), current.difficulty);
+                settings.change(|s| s.difficulty = next);
+            }
+            SettingsAction::Theme => settings.change(|s| s.theme = current.theme.next()),
+            SettingsAction::FirstClick => {
+                let next = next_in(FirstClick::value_variants(), current.first_click);
+                settings.change(|s| s.first_click = next);
+            }
+            SettingsAction::QuestionMarks => {
+                settings.change(|s| s.question_marks = !current.question_marks)
+            }
+            SettingsAction::Animations => {
+                settings.change(|s| s.animations.enabled = !current.animations.enabled)
+            }
+            SettingsAction::AnimationSpeed => {
+                let next = next_in(&ANIMATION_SPEEDS, current.animations.speed);
+                settings.change(|s| s.animations.speed = next);
+            }
+            SettingsAction::Volume => {
+                let next = next_volume(current.audio.master);
+                settings.change(|s| s.audio.master = next);
+            }
+            SettingsAction::Effects => {
+                let next = next_volume(current.audio.effects);
+                settings.change(|s| s.audio.effects = next);
+            }
+            SettingsAction::Muted => settings.change(|s| s.audio.muted = !current.audio.muted),
+            SettingsAction::Input(input_action) => {
+                rebinding.action = Some(*input_action);
+                rebinding.conflict = None;
+            }
+            SettingsAction::ResetInput => {
+                settings.change(|s| s.input = InputMap::default());
+                rebinding.conflict = None;
+            }
+            SettingsAction::Back => next_state.set(return_state.0),
+        }
+    }
+}
+
+// Bind the next key, mouse button or gamepad button that is pressed to the action waiting
+// for one. Buttons already bound to another action are refused.
+fn rebind_button(
+    mut keyboard_input: ResMut<ButtonInput<KeyCode>>,
+    mut mouse_button_input: ResMut<ButtonInput<MouseButton>>,
+    mut gamepads: Query<&mut Gamepad>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: SettingsMut,
+) {
+    let action = match rebinding.action {
+        Some(action) => action,
+        None => return,
+    };
+    let pressed = keyboard_input
+        .get_just_pressed()
+        .next()
+        .map(|key| InputButton::Key(*key))
+        .or_else(|| {
+            mouse_button_input
+                .get_just_pressed()
+                .next()
+                .map(|button| InputButton::Mouse(*button))
+        })
+        .or_else(|| {
+            gamepads
+                .iter()
+                .find_map(|gamepad| gamepad.get_just_pressed().next().copied())
+                .map(InputButton::Gamepad)
+        });
+    let button = match pressed {
+        Some(button) => button,
+        None => return,
+    };
+
+    // The press is used up, so it doesn't also do what the button is bound to.
+    match button {
+        InputButton::Key(key) => keyboard_input.clear_just_pressed(key),
+        InputButton::Mouse(mouse) => mouse_button_input.clear_just_pressed(mouse),
+        InputButton::Gamepad(gamepad_button) => {
+            for mut gamepad in gamepads.iter_mut() {
+                gamepad.digital_mut().clear_just_pressed(gamepad_button);
+            }
+        }
+    }
+
+    let mut input = settings.get().input;
+    rebinding.conflict = match input.bind(action, button) {
+        Ok(()) => {
+            settings.change(|s| s.input = input);
+            None
+        }
+        Err(other) => Some(format!(
+            "{} is already bound to {}.",
+            button.name(),
+            other.name()
+        )),
+    };
+    rebinding.action = None;
+}
+
+// Returns the value after the current one, wrapping around to the first. Values that
+// aren't in the list, such as speeds edited into the config file, go to the first.
+fn next_in<T: Copy + PartialEq>(values: &[T], current: T) -> T {
+    match values.iter().position(|value| *value == current) {
+        Some(index) => values[(index + 1) % values.len()],
+        None => values[0],
+    }
+}
+
+// Returns the volume one step louder, wrapping around to silence after the loudest.
+fn next_volume(volume: f32) -> f32 {
+    match volume >= 1.0 - VOLUME_STEP / 2.0 {
+        true => 0.0,
+        false => (((volume / VOLUME_STEP).round() + 1.0) * VOLUME_STEP).min(1.0),
+    }
+}
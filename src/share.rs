@@ -0,0 +1,43 @@
+use bevy::prelude::*;
+
+use crate::{
+    game::Game,
+    input::{ActionInput, InputAction},
+    settings::Settings,
+    states::GameState,
+};
+
+/// The `SharePlugin` copies the board to the clipboard as text when the share key in the
+/// settings, `C` by default, is pressed, so it can be pasted into a chat.
+pub struct SharePlugin;
+
+impl Plugin for SharePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            share_board.run_if(in_state(GameState::Playing).or(in_state(GameState::GameOver))),
+        );
+    }
+}
+
+// Copy what the player can see of the board to the clipboard.
+fn share_board(input: ActionInput, settings: Res<Settings>, game: Res<Game>) {
+    if !input.just_pressed(&settings.input, InputAction::Share) {
+        return;
+    }
+    match copy(game.player_view().to_string()) {
+        Ok(()) => info!("Copied the board to the clipboard"),
+        Err(err) => warn!("Unable to copy the board: {}", err),
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn copy(text: String) -> Result<(), arboard::Error> {
+    arboard::Clipboard::new()?.set_text(text)
+}
+
+// Browsers only let pages write to the clipboard from their own event handlers.
+#[cfg(target_arch = "wasm32")]
+fn copy(_text: String) -> Result<(), &'static str> {
+    Err("the clipboard can't be used in the browser")
+}
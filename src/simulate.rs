@@ -0,0 +1,287 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::ValueEnum;
+use minesweeper_core::{
+    Action, FirstClick, Game, HexTopology, PlayerView, Solver, SquareTopology, Topology,
+};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::Board;
+
+/// How the simulated player picks the tiles to reveal.
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Strategy {
+    /// Reveal hidden tiles at random.
+    Random,
+
+    /// Reveal the tiles the solver proves are safe and guess at random when stuck.
+    Solver,
+
+    /// Reveal the tiles the solver proves are safe and guess the tile least likely to be
+    /// a mine when stuck.
+    SolverProbability,
+}
+
+/// The formats the results can be written in.
+#[derive(ValueEnum, Clone, Copy, Default, Debug)]
+pub enum Format {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// Play many games without a window and report how each strategy does on each board.
+#[derive(clap::Args, Debug)]
+pub struct SimulateArgs {
+    #[clap(short = 'n', long, default_value_t = 100)]
+    /// The number of games played on each board with each strategy.
+    games: u64,
+
+    #[clap(long, value_enum, default_value = "solver")]
+    /// The strategy to play with. Give it more than once to compare strategies.
+    strategy: Vec<Strategy>,
+
+    #[clap(long, value_parser = parse_size, default_value = "16x16")]
+    /// The size of the board, such as `30x16`. Give it more than once to try many sizes.
+    size: Vec<(usize, usize)>,
+
+    #[clap(long, default_value = "0.15")]
+    /// The share of the tiles that are bombs. Give it more than once to try many densities.
+    density: Vec<f32>,
+
+    #[clap(long, default_value_t = 0)]
+    /// The seed of the first game. Each game after it uses the next seed, so every
+    /// strategy plays the same boards.
+    seed: u64,
+
+    #[clap(long, value_enum, default_value_t)]
+    /// How the bombs are kept away from the first revealed tile.
+    first_click: FirstClick,
+
+    #[clap(short, long, value_enum, default_value_t)]
+    /// The shape of the tiles and how they are connected.
+    board: Board,
+
+    #[clap(short, long)]
+    /// Connect the edges of the board to the opposite edges.
+    wrap: bool,
+
+    #[clap(long, value_enum, default_value_t)]
+    /// How the results are written.
+    format: Format,
+}
+
+// Parse a board size written as `WIDTHxHEIGHT`.
+fn parse_size(size: &str) -> Result<(usize, usize), String> {
+    let (width, height) = size
+        .split_once('x')
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got `{}`", size))?;
+    let parse = |value: &str| match value.trim().parse::<usize>() {
+        Ok(value) if value > 0 => Ok(value),
+        _ => Err(format!("`{}` isn't a positive number", value)),
+    };
+    Ok((parse(width)?, parse(height)?))
+}
+
+/// How a single simulated game went.
+struct Outcome {
+    won: bool,
+    three_bv: usize,
+    guesses: usize,
+    duration: Duration,
+}
+
+/// The results of playing many games on one board with one strategy.
+#[derive(Serialize, Debug)]
+pub struct Summary {
+    pub width: usize,
+    pub height: usize,
+    pub bombs: usize,
+    pub strategy: String,
+    pub games: u64,
+    pub wins: u64,
+    pub win_rate: f64,
+    pub average_3bv: f64,
+    pub average_guesses: f64,
+    pub average_ms: f64,
+}
+
+/// Play the games the arguments ask for and print the results.
+pub fn run(args: &SimulateArgs) {
+    // Make sure the edges of every board line up where they wrap.
+    let topology = topology(args);
+    if let Some((width, height)) = args
+        .size
+        .iter()
+        .find(|(width, height)| !topology.supports(*width, *height))
+    {
+        eprintln!(
+            "Unable to simulate {}x{}: wrapping hex boards need an even height",
+            width, height
+        );
+        return;
+    }
+
+    let mut summaries = Vec::new();
+    for &(width, height) in &args.size {
+        for &density in &args.density {
+            for &strategy in &args.strategy {
+                summaries.push(simulate(args, width, height, density, strategy));
+            }
+        }
+    }
+
+    match args.format {
+        Format::Csv => {
+            println!(
+                "width,height,bombs,strategy,games,wins,win_rate,average_3bv,average_guesses,average_ms"
+            );
+            for s in &summaries {
+                println!(
+                    "{},{},{},{},{},{},{:.4},{:.2},{:.2},{:.3}",
+                    s.width,
+                    s.height,
+                    s.bombs,
+                    s.strategy,
+                    s.games,
+                    s.wins,
+                    s.win_rate,
+                    s.average_3bv,
+                    s.average_guesses,
+                    s.average_ms
+                );
+            }
+        }
+        Format::Json => match serde_json::to_string_pretty(&summaries) {
+            Ok(json) => println!("{}", json),
+            Err(err) => eprintln!("Unable to write the results: {}", err),
+        },
+    }
+}
+
+// Returns how the tiles of the simulated boards are connected.
+fn topology(args: &SimulateArgs) -> Arc<dyn Topology> {
+    match args.board {
+        Board::Square => Arc::new(SquareTopology { wrap: args.wrap }),
+        Board::Hex => Arc::new(HexTopology { wrap: args.wrap }),
+    }
+}
+
+// Play every game on one board with one strategy.
+fn simulate(
+    args: &SimulateArgs,
+    width: usize,
+    height: usize,
+    density: f32,
+    strategy: Strategy,
+) -> Summary {
+    let topology = topology(args);
+    // There must be fewer bombs than tiles.
+    let tiles = width * height;
+    let bombs = ((tiles as f32 * density).round() as usize).min(tiles - 1);
+
+    let mut outcomes = Vec::new();
+    for index in 0..args.games {
+        let seed = args.seed.wrapping_add(index);
+        let game = Game::new(width, height, bombs)
+            .with_topology(topology.clone())
+            .with_first_click(args.first_click)
+            .with_seed(seed);
+        let mut rng = StdRng::seed_from_u64(seed);
+        outcomes.push(play(game, strategy, &mut rng));
+    }
+
+    let games = outcomes.len().max(1) as f64;
+    let wins = outcomes.iter().filter(|outcome| outcome.won).count() as u64;
+    let average = |value: fn(&Outcome) -> f64| outcomes.iter().map(value).sum::<f64>() / games;
+    Summary {
+        width,
+        height,
+        bombs,
+        strategy: strategy
+            .to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default(),
+        games: args.games,
+        wins,
+        win_rate: wins as f64 / games,
+        average_3bv: average(|outcome| outcome.three_bv as f64),
+        average_guesses: average(|outcome| outcome.guesses as f64),
+        average_ms: average(|outcome| outcome.duration.as_secs_f64() * 1000.0),
+    }
+}
+
+// Play a game until it's won or lost. The first tile is picked at random and isn't
+// counted as a guess, since every game has to start somewhere.
+fn play(mut game: Game, strategy: Strategy, rng: &mut StdRng) -> Outcome {
+    let start = Instant::now();
+    let mut solver = Solver::new();
+    let mut guesses = 0;
+    let mut opening = true;
+
+    while !game.game_over {
+        let view = game.player_view();
+        let safe = match strategy {
+            Strategy::Random => Vec::new(),
+            Strategy::Solver | Strategy::SolverProbability => solver.safe_tiles(&view),
+        };
+        let targets = match safe.is_empty() {
+            false => safe,
+            true => {
+                if !opening {
+                    guesses += 1;
+                }
+                let guess = match strategy {
+                    Strategy::SolverProbability if !opening => safest_tile(&solver, &view),
+                    _ => random_tile(&solver, &view, rng),
+                };
+                opening = false;
+                match guess {
+                    Some(tile) => vec![tile],
+                    None => break,
+                }
+            }
+        };
+
+        for (x, y) in targets {
+            if game.game_over {
+                break;
+            }
+            // The view is stale once the first target is revealed, so ask the game.
+            if game.tile(x, y).revealed {
+                continue;
+            }
+            game.perform_action(x, y, Action::Reveal)
+                .expect("hidden tiles without flags can always be revealed");
+        }
+    }
+
+    Outcome {
+        won: game.game_won,
+        three_bv: game.three_bv(),
+        guesses,
+        duration: start.elapsed(),
+    }
+}
+
+// Returns a hidden tile that isn't known to be a mine, picked at random.
+fn random_tile(solver: &Solver, view: &PlayerView, rng: &mut StdRng) -> Option<(usize, usize)> {
+    let tiles = solver.unknown_tiles(view);
+    match tiles.is_empty() {
+        true => None,
+        false => Some(tiles[rng.random_range(0..tiles.len())]),
+    }
+}
+
+// Returns the hidden tile least likely to be a mine. Ties go to the first tile, so the
+// same seed always plays the same game.
+fn safest_tile(solver: &Solver, view: &PlayerView) -> Option<(usize, usize)> {
+    solver
+        .probabilities(view)
+        .into_iter()
+        .min_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)))
+        .map(|(tile, _)| tile)
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use bevy::{audio::Volume, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::ASSET_PREFIX,
+    input::{ActionInput, InputAction},
+    settings::{Settings, SettingsMut},
+};
+
+/// How much the volume changes with each key press.
+const VOLUME_STEP: f32 = 0.1;
+
+/// The sound effects played by the game.
+#[derive(Event, Clone, Copy, Eq, PartialEq, Hash, Debug)]
+pub enum Sound {
+    /// A single tile was revealed.
+    Reveal,
+
+    /// Many tiles were revealed at once.
+    FloodReveal,
+
+    /// A tile was flagged or marked with a question mark.
+    Flag,
+
+    /// A mark was removed from a tile.
+    Unflag,
+
+    /// The tiles around a number were revealed.
+    Chord,
+
+    /// A mine was revealed.
+    Explosion,
+
+    /// The game was won.
+    Win,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 7] = [
+        Sound::Reveal,
+        Sound::FloodReveal,
+        Sound::Flag,
+        Sound::Unflag,
+        Sound::Chord,
+        Sound::Explosion,
+        Sound::Win,
+    ];
+
+    /// Returns the asset path of the sound.
+    pub fn path(&self) -> String {
+        let name = match self {
+            Sound::Reveal => "reveal",
+            Sound::FloodReveal => "flood_reveal",
+            Sound::Flag => "flag",
+            Sound::Unflag => "unflag",
+            Sound::Chord => "chord",
+            Sound::Explosion => "explosion",
+            Sound::Win => "win",
+        };
+        format!("{}sounds/{}.wav", ASSET_PREFIX, name)
+    }
+}
+
+/// The `AudioSettings` control how loud the game is.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct AudioSettings {
+    /// The volume of everything, from 0.0 to 1.0.
+    pub master: f32,
+
+    /// The volume of sound effects, from 0.0 to 1.0.
+    pub effects: f32,
+
+    /// Whether all sounds are muted.
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master: 1.0,
+            effects: 0.8,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    /// Returns the volume sound effects are played at.
+    pub fn effects_volume(&self) -> f32 {
+        match self.muted {
+            true => 0.0,
+            false => self.master * self.effects,
+        }
+    }
+}
+
+// The handles of all the sound effects, loaded up front so they are ready to play.
+#[derive(Resource)]
+struct SoundEffects(HashMap<Sound, Handle<AudioSource>>);
+
+impl FromWorld for SoundEffects {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        SoundEffects(
+            Sound::ALL
+                .into_iter()
+                .map(|sound| (sound, asset_server.load(sound.path())))
+                .collect(),
+        )
+    }
+}
+
+/// The `SoundPlugin` plays the sound effects sent as `Sound` events. The mute and volume
+/// controls in the settings change the audio settings, `M`, `-`, `=`, `[` and `]` by
+/// default.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Sound>()
+            .init_resource::<SoundEffects>()
+            .add_systems(Update, (change_volume, play_sounds).chain());
+    }
+}
+
+// Play the sounds that were sent this frame.
+fn play_sounds(
+    mut commands: Commands,
+    mut events: EventReader<Sound>,
+    settings: Res<Settings>,
+    effects: Res<SoundEffects>,
+) {
+    let volume = settings.audio.effects_volume();
+    for sound in events.read() {
+        if volume <= 0.0 {
+            continue;
+        }
+        commands.spawn((
+            Name::new(format!("Sound {:?}", sound)),
+            AudioPlayer::new(effects.0[sound].clone()),
+            PlaybackSettings::DESPAWN.with_volume(Volume::Linear(volume)),
+        ));
+    }
+}
+
+// Change the audio settings with the controls bound to them.
+fn change_volume(input: ActionInput, mut settings: SettingsMut) {
+    let step = |volume: f32, change: f32| (volume + change).clamp(0.0, 1.0);
+    let mut audio = settings.get().audio;
+    for action in input.get_just_pressed(&settings.get().input) {
+        match action {
+            InputAction::Mute => audio.muted = !audio.muted,
+            InputAction::VolumeDown => audio.master = step(audio.master, -VOLUME_STEP),
+            InputAction::VolumeUp => audio.master = step(audio.master, VOLUME_STEP),
+            InputAction::EffectsDown => audio.effects = step(audio.effects, -VOLUME_STEP),
+            InputAction::EffectsUp => audio.effects = step(audio.effects, VOLUME_STEP),
+            _ => continue,
+        }
+    }
+    if audio != settings.get().audio {
+        settings.change(|s| s.audio = audio);
+        info!("Audio settings: {:?}", audio);
+    }
+}
@@ -4,10 +4,22 @@ use bevy::state::state::States;
 /// The `GameState` enum represents the different states of the game.
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 pub enum GameState {
-    /// The game is currently being played.
+    /// The main menu shown when the game starts.
     #[default]
+    MainMenu,
+
+    /// The game is currently being played.
     Playing,
 
+    /// The game is paused and the board is hidden.
+    Paused,
+
     /// The game is over, either won or lost.
     GameOver,
+
+    /// The settings screen.
+    Settings,
+
+    /// The statistics of past games.
+    Stats,
 }
@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use minesweeper_core::{TileShape, Variant};
+use serde::{Deserialize, Serialize};
+
+use crate::{config, game::Game, minesweeper_plugin::GameTimer, states::GameState};
+
+/// The name of the file the statistics are saved to.
+const STATS_CONFIG: &str = "stats.ron";
+
+/// The `Stats` resource holds the results of past games. It's saved after every game.
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Stats {
+    /// The number of games that were finished.
+    pub played: u32,
+
+    /// The number of games that were won.
+    pub won: u32,
+
+    /// The fastest win in seconds for each kind of board.
+    pub best_times: HashMap<String, f32>,
+}
+
+impl Stats {
+    /// Record a finished game.
+    pub fn record(&mut self, board: String, won: bool, seconds: f32) {
+        self.played += 1;
+        if !won {
+            return;
+        }
+        self.won += 1;
+        let best = self.best_times.entry(board).or_insert(seconds);
+        *best = best.min(seconds);
+    }
+
+    /// Returns the percentage of games that were won.
+    pub fn win_rate(&self) -> f32 {
+        match self.played {
+            0 => 0.0,
+            played => self.won as f32 * 100.0 / played as f32,
+        }
+    }
+}
+
+/// Returns a description of the board that tells apart the boards best times are kept
+/// for, such as `30x16 square, 20 bombs`.
+pub fn board_name(game: &Game) -> String {
+    let mut name = format!("{}x{}", game.width, game.height);
+    if game.depth > 1 {
+        name.push_str(&format!("x{}", game.depth));
+    }
+    name.push_str(match game.topology.shape() {
+        TileShape::Square => " square",
+        TileShape::Hex => " hex",
+    });
+    if game.topology.wraps() {
+        name.push_str(" wrapping");
+    }
+    if game.variant == Variant::MultiMine {
+        name.push_str(" multi-mine");
+    }
+    if game.mask.is_some() {
+        name.push_str(" masked");
+    }
+    if game.minefield.is_some() {
+        name.push_str(" saved");
+    }
+    format!("{}, {} bombs", name, game.bombs)
+}
+
+/// The `StatsPlugin` records every finished game.
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(config::load::<Stats>(STATS_CONFIG))
+            .add_systems(OnEnter(GameState::GameOver), record_game);
+    }
+}
+
+// Record the game that just finished and save the statistics.
+fn record_game(game: Res<Game>, timer: Res<GameTimer>, mut stats: ResMut<Stats>) {
+    stats.record(board_name(&game), game.game_won, timer.0.elapsed_secs());
+    if let Err(err) = config::save(STATS_CONFIG, &*stats) {
+        error!("Unable to save the statistics: {}", err);
+    }
+}
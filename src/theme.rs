@@ -0,0 +1,233 @@
+use bevy::prelude::*;
+use clap::ValueEnum;
+use minesweeper_core::TileShape;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::ASSET_PREFIX,
+    colors::{FRAPPE, LATTE, MACCHIATO, MOCHA, Palette},
+    input::{ActionInput, InputAction},
+    popup::{PopupButton, PopupPanel, PopupText},
+    settings::{Settings, SettingsMut},
+};
+
+/// The name of the image that holds all the tile images of a theme.
+pub const TILESET: &str = "tiles.png";
+
+/// The images in a tile set, in the order they appear from left to right.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileImage {
+    Closed,
+    Open,
+    Flag,
+    Question,
+    Bomb,
+}
+
+impl TileImage {
+    /// The number of images in a tile set.
+    pub const COUNT: u32 = 5;
+
+    /// Returns the index of the image in the tile set.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Returns the size of a single image in a tile set of the given shape.
+    pub fn size(shape: TileShape) -> UVec2 {
+        match shape {
+            TileShape::Square => UVec2::new(32, 32),
+            TileShape::Hex => UVec2::new(32, 37),
+        }
+    }
+
+    /// Returns the layout of a tile set of the given shape.
+    pub fn layout(shape: TileShape) -> TextureAtlasLayout {
+        TextureAtlasLayout::from_grid(Self::size(shape), Self::COUNT, 1, None, None)
+    }
+}
+
+/// The `Theme` resource decides how the game looks: the colors, the tile images and the
+/// font used for all the text.
+#[derive(Resource, Clone, Debug)]
+pub struct Theme {
+    /// The name shown to players.
+    pub name: String,
+
+    /// The colors used to draw the game.
+    pub palette: Palette,
+
+    /// The asset path of the directory holding the tile set. Hexagonal tiles are found in
+    /// the `hex` directory inside of it.
+    pub tiles: String,
+
+    /// The font used for all text. The default handle uses Bevy's built in font.
+    pub font: Handle<Font>,
+}
+
+impl Theme {
+    /// Returns the asset path of the tile set for the given shape.
+    pub fn tileset_path(&self, shape: TileShape) -> String {
+        match shape {
+            TileShape::Square => format!("{}{}", self.tiles, TILESET),
+            TileShape::Hex => format!("{}hex/{}", self.tiles, TILESET),
+        }
+    }
+
+    /// Returns the text font for the given size.
+    pub fn text_font(&self, font_size: f32) -> TextFont {
+        TextFont {
+            font: self.font.clone(),
+            font_size,
+            ..default()
+        }
+    }
+}
+
+/// The themes that come with the game.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub enum BuiltinTheme {
+    /// Catppuccin Latte, a light theme.
+    Latte,
+
+    /// Catppuccin Frappé.
+    Frappe,
+
+    /// Catppuccin Macchiato.
+    Macchiato,
+
+    /// Catppuccin Mocha, the darkest Catppuccin theme.
+    #[default]
+    Mocha,
+
+    /// The gray look of the classic Windows game.
+    Classic,
+}
+
+impl BuiltinTheme {
+    pub const ALL: [BuiltinTheme; 5] = [
+        BuiltinTheme::Latte,
+        BuiltinTheme::Frappe,
+        BuiltinTheme::Macchiato,
+        BuiltinTheme::Mocha,
+        BuiltinTheme::Classic,
+    ];
+
+    /// Returns the name shown to players.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuiltinTheme::Latte => "Catppuccin Latte",
+            BuiltinTheme::Frappe => "Catppuccin Frappé",
+            BuiltinTheme::Macchiato => "Catppuccin Macchiato",
+            BuiltinTheme::Mocha => "Catppuccin Mocha",
+            BuiltinTheme::Classic => "Classic",
+        }
+    }
+
+    /// Returns the built in theme that follows this one.
+    pub fn next(&self) -> BuiltinTheme {
+        let index = Self::ALL
+            .iter()
+            .position(|theme| theme == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Returns the built in theme with the given name, if any.
+    pub fn from_name(name: &str) -> Option<BuiltinTheme> {
+        Self::ALL.into_iter().find(|theme| theme.name() == name)
+    }
+
+    /// Create the theme.
+    pub fn theme(&self) -> Theme {
+        let (palette, directory) = match self {
+            BuiltinTheme::Latte => (LATTE, "latte/"),
+            BuiltinTheme::Frappe => (FRAPPE, "frappe/"),
+            BuiltinTheme::Macchiato => (MACCHIATO, "macchiato/"),
+            BuiltinTheme::Mocha => (MOCHA, ""),
+            BuiltinTheme::Classic => (Palette::classic(), "classic/"),
+        };
+        Theme {
+            name: self.name().to_string(),
+            palette,
+            tiles: format!("{}{}", ASSET_PREFIX, directory),
+            font: Handle::default(),
+        }
+    }
+}
+
+/// The `ThemePlugin` adds the `Theme` resource and keeps the screen in sync with it.
+/// Pressing the theme key in the settings, `T` by default, switches to the next built in
+/// theme.
+pub struct ThemePlugin {
+    /// The theme used when the game starts.
+    pub theme: BuiltinTheme,
+}
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.theme.theme())
+            .add_systems(Update, cycle_theme)
+            .add_systems(Update, follow_settings.run_if(resource_changed::<Settings>))
+            .add_systems(Update, apply_theme.run_if(resource_changed::<Theme>));
+    }
+}
+
+// Switch to the next built in theme when the theme key is pressed. A theme pack is
+// followed by the theme in the settings.
+fn cycle_theme(input: ActionInput, mut settings: SettingsMut, mut theme: ResMut<Theme>) {
+    if !input.just_pressed(&settings.get().input, InputAction::Theme) {
+        return;
+    }
+    let next = match BuiltinTheme::from_name(&theme.name) {
+        Some(current) => current.next(),
+        None => settings.get().theme,
+    };
+    info!("Switching to the {} theme", next.name());
+    settings.change(|s| s.theme = next);
+    *theme = next.theme();
+}
+
+// Switch to the theme picked on the settings screen. The theme the game started with is
+// left alone, since it may come from a theme pack.
+fn follow_settings(
+    settings: Res<Settings>,
+    mut theme: ResMut<Theme>,
+    mut current: Local<Option<BuiltinTheme>>,
+) {
+    let previous = current.replace(settings.theme);
+    if previous.is_some_and(|previous| previous != settings.theme) {
+        *theme = settings.theme.theme();
+    }
+}
+
+// Restyle the background and any open popups. The board restyles its own tiles.
+fn apply_theme(
+    theme: Res<Theme>,
+    mut clear_color: ResMut<ClearColor>,
+    mut panel_query: Query<(&mut BackgroundColor, &mut BorderColor), With<PopupPanel>>,
+    mut button_query: Query<
+        (&mut BackgroundColor, &mut BorderColor),
+        (With<PopupButton>, Without<PopupPanel>),
+    >,
+    mut text_query: Query<(&mut TextFont, &mut TextColor, &PopupText)>,
+) {
+    let palette = &theme.palette;
+    clear_color.0 = palette.background;
+
+    for (mut background, mut border) in panel_query.iter_mut() {
+        background.0 = palette.panel;
+        border.0 = palette.border;
+    }
+    for (mut background, mut border) in button_query.iter_mut() {
+        background.0 = palette.button;
+        border.0 = palette.border;
+    }
+    for (mut font, mut color, text) in text_query.iter_mut() {
+        font.font = theme.font.clone();
+        color.0 = match text {
+            PopupText::Message => palette.text,
+            PopupText::Button => palette.button_text,
+        };
+    }
+}
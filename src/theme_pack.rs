@@ -0,0 +1,402 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use bevy::asset::io::AssetSourceBuilder;
+use bevy::asset::ron;
+use bevy::prelude::*;
+use minesweeper_core::TileShape;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    colors::Palette,
+    game::Game,
+    theme::{BuiltinTheme, TILESET, Theme, TileImage},
+};
+
+/// The name of the asset source that reads from the theme pack directory.
+pub const THEME_PACK_SOURCE: &str = "theme";
+
+/// The name of the manifest file inside of a theme pack.
+const MANIFEST: &str = "theme.ron";
+
+/// How often the theme pack directory is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The `ThemeManifest` describes a theme pack. It's read from the `theme.ron` file at the
+/// root of the theme pack directory, for example:
+///
+/// ```ron
+/// (
+///     name: "Neon",
+///     base: Mocha,
+///     colors: (
+///         background: "#101018",
+///         numbers: ["#00ffff", "#00ff00", "#ff0055"],
+///     ),
+///     font: Some("neon.ttf"),
+/// )
+/// ```
+///
+/// The tile set is read from `tiles.png` in the same directory, with hexagonal tiles in
+/// `hex/tiles.png`. A tile set holds the closed, open, flag, question and bomb images from
+/// left to right.
+#[derive(Deserialize, Debug)]
+pub struct ThemeManifest {
+    /// The name shown to players.
+    pub name: String,
+
+    /// The built in theme whose colors are used when they aren't overridden.
+    #[serde(default)]
+    pub base: BuiltinTheme,
+
+    /// The colors that replace those of the base theme.
+    #[serde(default)]
+    pub colors: ColorOverrides,
+
+    /// A font file inside the theme pack used for all text.
+    #[serde(default)]
+    pub font: Option<String>,
+}
+
+/// The colors a theme pack can override, written as hex strings such as `#1e1e2e`. The
+/// fields match those of [`Palette`].
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct ColorOverrides {
+    pub background: Option<String>,
+    pub text: Option<String>,
+    pub edge: Option<String>,
+    pub highlight: Option<String>,
+    pub panel: Option<String>,
+    pub border: Option<String>,
+    pub button: Option<String>,
+    pub button_hovered: Option<String>,
+    pub button_pressed: Option<String>,
+    pub button_text: Option<String>,
+
+    /// The colors of the numbers, starting with 1. Numbers without a color keep the color
+    /// of the base theme.
+    pub numbers: Vec<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum ThemePackError {
+    #[error("Unable to read {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("Unable to parse {0}: {1}")]
+    Manifest(PathBuf, ron::error::SpannedError),
+    #[error("Missing tile set {0}")]
+    MissingTileset(PathBuf),
+    #[error("Unable to read the tile set {0}: {1}")]
+    Tileset(PathBuf, image::ImageError),
+    #[error("The tile set {0} is {1}x{2} but should be {3}x{4}")]
+    TilesetSize(PathBuf, u32, u32, u32, u32),
+    #[error("Missing font {0}")]
+    MissingFont(PathBuf),
+    #[error("The color {1:?} for {0} isn't a valid hex color")]
+    InvalidColor(String, String),
+    #[error("There are {0} number colors but only 9 are used")]
+    TooManyNumbers(usize),
+}
+
+impl ThemeManifest {
+    /// Read the manifest from the given theme pack directory.
+    pub fn load(directory: &Path) -> Result<ThemeManifest, ThemePackError> {
+        let path = directory.join(MANIFEST);
+        let text =
+            std::fs::read_to_string(&path).map_err(|err| ThemePackError::Io(path.clone(), err))?;
+        ron::from_str(&text).map_err(|err| ThemePackError::Manifest(path, err))
+    }
+
+    /// Returns the palette of the base theme with the overridden colors replaced.
+    pub fn palette(&self) -> Result<Palette, Vec<ThemePackError>> {
+        let mut palette = self.base.theme().palette;
+        let mut errors = Vec::new();
+        let colors = &self.colors;
+        let overrides = [
+            ("background", &colors.background, &mut palette.background),
+            ("text", &colors.text, &mut palette.text),
+            ("edge", &colors.edge, &mut palette.edge),
+            ("highlight", &colors.highlight, &mut palette.highlight),
+            ("panel", &colors.panel, &mut palette.panel),
+            ("border", &colors.border, &mut palette.border),
+            ("button", &colors.button, &mut palette.button),
+            (
+                "button_hovered",
+                &colors.button_hovered,
+                &mut palette.button_hovered,
+            ),
+            (
+                "button_pressed",
+                &colors.button_pressed,
+                &mut palette.button_pressed,
+            ),
+            ("button_text", &colors.button_text, &mut palette.button_text),
+        ];
+        for (name, value, color) in overrides {
+            if let Some(value) = value {
+                match parse_color(name, value) {
+                    Ok(parsed) => *color = parsed,
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+
+        if colors.numbers.len() > palette.numbers.len() {
+            errors.push(ThemePackError::TooManyNumbers(colors.numbers.len()));
+        }
+        for (i, (value, color)) in colors.numbers.iter().zip(&mut palette.numbers).enumerate() {
+            match parse_color(&format!("number {}", i + 1), value) {
+                Ok(parsed) => *color = parsed,
+                Err(err) => errors.push(err),
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(palette),
+            false => Err(errors),
+        }
+    }
+}
+
+// Parse a hex color from the manifest.
+fn parse_color(name: &str, value: &str) -> Result<Color, ThemePackError> {
+    Srgba::hex(value)
+        .map(Color::from)
+        .map_err(|_| ThemePackError::InvalidColor(name.to_string(), value.to_string()))
+}
+
+/// Load the theme pack in the given directory, making sure all the files needed to draw
+/// tiles of the given shape exist. Every problem found is returned so they can all be
+/// fixed at once.
+pub fn load_theme_pack(
+    directory: &Path,
+    shape: TileShape,
+    asset_server: &AssetServer,
+) -> Result<Theme, Vec<ThemePackError>> {
+    let manifest = ThemeManifest::load(directory).map_err(|err| vec![err])?;
+    let mut errors = Vec::new();
+
+    let palette = manifest.palette().unwrap_or_else(|mut palette_errors| {
+        errors.append(&mut palette_errors);
+        manifest.base.theme().palette
+    });
+
+    let tileset = match shape {
+        TileShape::Square => directory.join(TILESET),
+        TileShape::Hex => directory.join("hex").join(TILESET),
+    };
+    let size = TileImage::size(shape) * UVec2::new(TileImage::COUNT, 1);
+    match image::image_dimensions(&tileset) {
+        Ok((width, height)) if width != size.x || height != size.y => {
+            errors.push(ThemePackError::TilesetSize(
+                tileset, width, height, size.x, size.y,
+            ));
+        }
+        Ok(_) => {}
+        Err(_) if !tileset.is_file() => errors.push(ThemePackError::MissingTileset(tileset)),
+        Err(err) => errors.push(ThemePackError::Tileset(tileset, err)),
+    }
+
+    let font = match &manifest.font {
+        Some(font) if !directory.join(font).is_file() => {
+            errors.push(ThemePackError::MissingFont(directory.join(font)));
+            Handle::default()
+        }
+        Some(font) => asset_server.load(format!("{}://{}", THEME_PACK_SOURCE, font)),
+        None => Handle::default(),
+    };
+
+    match errors.is_empty() {
+        true => Ok(Theme {
+            name: manifest.name,
+            palette,
+            tiles: format!("{}://", THEME_PACK_SOURCE),
+            font,
+        }),
+        false => Err(errors),
+    }
+}
+
+/// The `ThemePackPlugin` loads a theme pack from a directory on disk and reloads it
+/// whenever the files in the directory change. Problems with the theme pack are shown
+/// on screen and the current theme is kept until they are fixed.
+///
+/// The plugin registers an asset source, so it must be added before the `AssetPlugin`.
+pub struct ThemePackPlugin {
+    /// The directory holding the manifest and the tile sets.
+    pub directory: PathBuf,
+}
+
+impl Plugin for ThemePackPlugin {
+    fn build(&self, app: &mut App) {
+        // The asset source is relative to the executable unless the path is absolute.
+        let directory = std::path::absolute(&self.directory).unwrap_or(self.directory.clone());
+        app.register_asset_source(
+            THEME_PACK_SOURCE,
+            AssetSourceBuilder::platform_default(&directory.to_string_lossy(), None),
+        )
+        .insert_resource(ThemePack {
+            directory,
+            files: Vec::new(),
+            timer: Timer::new(WATCH_INTERVAL, TimerMode::Repeating),
+        })
+        .add_systems(Startup, load_pack)
+        .add_systems(Update, watch_pack);
+    }
+}
+
+/// The `ThemePack` resource tracks the theme pack directory and the files in it.
+#[derive(Resource)]
+struct ThemePack {
+    directory: PathBuf,
+
+    /// The files in the directory when it was last loaded, with their modified times.
+    files: Vec<(PathBuf, SystemTime)>,
+
+    timer: Timer,
+}
+
+impl ThemePack {
+    // Returns the files in the theme pack along with when they were modified.
+    fn scan(&self) -> Vec<(PathBuf, SystemTime)> {
+        let mut files: Vec<(PathBuf, SystemTime)> =
+            [self.directory.clone(), self.directory.join("hex")]
+                .iter()
+                .filter_map(|directory| std::fs::read_dir(directory).ok())
+                .flatten()
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let metadata = entry.metadata().ok()?;
+                    match metadata.is_file() {
+                        true => Some((entry.path(), metadata.modified().ok()?)),
+                        false => None,
+                    }
+                })
+                .collect();
+        files.sort();
+        files
+    }
+}
+
+/// A marker for the panel that lists the problems with the theme pack.
+#[derive(Component)]
+pub struct ThemePackErrors;
+
+// Load the theme pack when the game starts.
+fn load_pack(
+    mut commands: Commands,
+    mut pack: ResMut<ThemePack>,
+    mut theme: ResMut<Theme>,
+    game: Res<Game>,
+    asset_server: Res<AssetServer>,
+    error_query: Query<Entity, With<ThemePackErrors>>,
+) {
+    pack.files = pack.scan();
+    apply_pack(
+        &mut commands,
+        &pack,
+        &mut theme,
+        &game,
+        &asset_server,
+        &error_query,
+    );
+}
+
+// Check the theme pack for changes and reload it when the files have changed.
+fn watch_pack(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pack: ResMut<ThemePack>,
+    mut theme: ResMut<Theme>,
+    game: Res<Game>,
+    asset_server: Res<AssetServer>,
+    error_query: Query<Entity, With<ThemePackErrors>>,
+) {
+    if !pack.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let files = pack.scan();
+    if files == pack.files {
+        return;
+    }
+
+    // Reload the changed tile sets and fonts so tiles already on screen are updated.
+    for (path, modified) in &files {
+        if pack.files.contains(&(path.clone(), *modified)) {
+            continue;
+        }
+        if let Ok(relative) = path.strip_prefix(&pack.directory) {
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if !relative.ends_with(MANIFEST) {
+                debug!("Reloading {}", relative);
+                asset_server.reload(format!("{}://{}", THEME_PACK_SOURCE, relative));
+            }
+        }
+    }
+    pack.files = files;
+
+    info!("Theme pack changed, reloading");
+    apply_pack(
+        &mut commands,
+        &pack,
+        &mut theme,
+        &game,
+        &asset_server,
+        &error_query,
+    );
+}
+
+// Load the theme pack and make it the current theme. When there are problems, the current
+// theme is kept and the problems are shown on screen.
+fn apply_pack(
+    commands: &mut Commands,
+    pack: &ThemePack,
+    theme: &mut Theme,
+    game: &Game,
+    asset_server: &AssetServer,
+    error_query: &Query<Entity, With<ThemePackErrors>>,
+) {
+    for entity in error_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let errors = match load_theme_pack(&pack.directory, game.topology.shape(), asset_server) {
+        Ok(loaded) => {
+            info!("Loaded the {} theme pack", loaded.name);
+            *theme = loaded;
+            return;
+        }
+        Err(errors) => errors,
+    };
+
+    let mut message = format!(
+        "Unable to load the theme pack {}:",
+        pack.directory.display()
+    );
+    for err in &errors {
+        error!("{}", err);
+        message.push_str(&format!("\n- {}", err));
+    }
+    commands.spawn((
+        Name::new("Theme Pack Errors"),
+        ThemePackErrors,
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Px(10.0),
+            right: Val::Px(10.0),
+            border: UiRect::all(Val::Px(2.0)),
+            padding: UiRect::all(Val::Px(10.0)),
+            ..default()
+        },
+        BorderColor(theme.palette.number(3)),
+        BackgroundColor(theme.palette.panel),
+        children![(
+            Text::new(message),
+            theme.text_font(16.0),
+            TextColor(theme.palette.text),
+        )],
+    ));
+}